@@ -0,0 +1,145 @@
+use super::{LocalDate, LocalDateTime, LocalTime, OffsetDateTime};
+
+/// Reads a number that must be exactly the given number of digits, ie the 05 in 1979-05-27
+fn parse_digits(value: &str, digits: usize) -> Option<u32> {
+    if value.len() != digits || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+fn is_leap_year(year: u16) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+impl LocalDate {
+    /// Parses a YYYY-MM-DD date, None is returned if the date is malformed or doesn't exist
+    pub(crate) fn parse(value: &str) -> Option<LocalDate> {
+        let mut parts = value.split('-');
+        let year = parse_digits(parts.next()?, 4)? as u16;
+        let month = parse_digits(parts.next()?, 2)? as u8;
+        let day = parse_digits(parts.next()?, 2)? as u8;
+        if parts.next().is_some() {
+            return None;
+        }
+        let days_in_month = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if is_leap_year(year) => 29,
+            2 => 28,
+            _ => return None,
+        };
+        if day == 0 || day > days_in_month {
+            return None;
+        }
+        Some(LocalDate { year, month, day })
+    }
+}
+
+impl LocalTime {
+    /// Parses a HH:MM:SS time with optional fractional seconds, None is returned if the time is malformed or out of range
+    pub(crate) fn parse(value: &str) -> Option<LocalTime> {
+        let (value, fraction) = match value.find('.') {
+            Some(index) => (&value[..index], Some(&value[index + 1..])),
+            None => (value, None),
+        };
+        let mut parts = value.split(':');
+        let hour = parse_digits(parts.next()?, 2)? as u8;
+        let minute = parse_digits(parts.next()?, 2)? as u8;
+        let second = parse_digits(parts.next()?, 2)? as u8;
+        if parts.next().is_some() {
+            return None;
+        }
+        // A leap second can only be added at the end of a minute
+        if hour > 23 || minute > 59 || second > 60 || (second == 60 && minute != 59) {
+            return None;
+        }
+        let nanosecond = match fraction {
+            Some(fraction) => {
+                if fraction.is_empty() || !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+                    return None;
+                }
+                // Precision beyond nanoseconds is truncated
+                fraction
+                    .bytes()
+                    .chain(std::iter::repeat(b'0'))
+                    .take(9)
+                    .fold(0, |nanosecond, digit| nanosecond * 10 + (digit - b'0') as u32)
+            }
+            None => 0,
+        };
+        Some(LocalTime {
+            hour,
+            minute,
+            second,
+            nanosecond,
+        })
+    }
+}
+
+impl OffsetDateTime {
+    /// Parses a Z or +HH:MM offset into minutes from UTC
+    pub(crate) fn parse_offset(value: &str) -> Option<i16> {
+        if value == "Z" || value == "z" {
+            return Some(0);
+        }
+        let sign = match value.get(..1)? {
+            "+" => 1,
+            "-" => -1,
+            _ => return None,
+        };
+        let mut parts = value[1..].split(':');
+        let hour = parse_digits(parts.next()?, 2)? as i16;
+        let minute = parse_digits(parts.next()?, 2)? as i16;
+        if parts.next().is_some() || hour > 23 || minute > 59 {
+            return None;
+        }
+        Some(sign * (hour * 60 + minute))
+    }
+}
+
+impl std::fmt::Display for LocalDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{:04}-{:02}-{:02}",
+            self.year, self.month, self.day
+        ))
+    }
+}
+
+impl std::fmt::Display for LocalTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{:02}:{:02}:{:02}",
+            self.hour, self.minute, self.second
+        ))?;
+        if self.nanosecond != 0 {
+            let fraction = format!("{:09}", self.nanosecond);
+            f.write_fmt(format_args!(".{}", fraction.trim_end_matches('0')))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for LocalDateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}T{}", self.date, self.time))
+    }
+}
+
+impl std::fmt::Display for OffsetDateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}T{}", self.date, self.time))?;
+        if self.offset == 0 {
+            return f.write_str("Z");
+        }
+        let sign = if self.offset < 0 { '-' } else { '+' };
+        let offset = self.offset.abs();
+        f.write_fmt(format_args!(
+            "{}{:02}:{:02}",
+            sign,
+            offset / 60,
+            offset % 60
+        ))
+    }
+}
@@ -0,0 +1,327 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+
+use indexmap::map::Entry;
+use indexmap::IndexMap;
+
+use super::error::format_key;
+use super::value::{into_owned_table, sort_table};
+use super::{Document, DocumentBuilder, Error, ErrorKind, Parser, TomlPair, TomlTable, TomlValue};
+
+impl<'a> DocumentBuilder<'a> {
+    pub fn new() -> DocumentBuilder<'a> {
+        DocumentBuilder {
+            root: IndexMap::new(),
+            current_table: Vec::new(),
+            defined_tables: HashSet::new(),
+            dotted_tables: HashSet::new(),
+            arrays_of_tables: HashSet::new(),
+            inline_tables: HashSet::new(),
+            key_lines: HashMap::new(),
+        }
+    }
+
+    /// Builds the error for a name that has already been given a value
+    fn duplicate_key_error(
+        key_lines: &HashMap<Vec<Cow<'a, str>>, usize>,
+        key: &[Cow<'a, str>],
+        line_number: usize,
+    ) -> Error {
+        let first_line_number = key_lines.get(key).copied().unwrap_or(line_number);
+        Error::new(
+            ErrorKind::DuplicateKey {
+                key: format_key(key),
+                line_number,
+                first_line_number,
+            },
+            None,
+        )
+    }
+
+    /// Remembers the line that the name and any tables along its path were first defined on
+    fn record_key_line(&mut self, key: &[Cow<'a, str>], line_number: usize) {
+        for index in 0..key.len() {
+            self.key_lines
+                .entry(key[..=index].to_vec())
+                .or_insert(line_number);
+        }
+    }
+
+    /// Returns the table at the end of the path, any table along the path that doesn't exist is created
+    /// When the path passes through an array of tables the last table in the array is used
+    fn get_or_create_table<'b>(
+        table: &'b mut TomlTable<'a>,
+        path: &[Cow<'a, str>],
+        arrays_of_tables: &HashSet<Vec<Cow<'a, str>>>,
+        inline_tables: &HashSet<Vec<Cow<'a, str>>>,
+        key_lines: &HashMap<Vec<Cow<'a, str>>, usize>,
+        line_number: usize,
+    ) -> Result<&'b mut TomlTable<'a>, Error> {
+        let mut table = table;
+        for (index, name) in path.iter().enumerate() {
+            if inline_tables.contains(&path[..=index]) {
+                // Inline tables are sealed, nothing can be added to them after they are defined
+                return Err(Error::new(ErrorKind::TableRedefined(line_number), None));
+            }
+            let value = table
+                .entry(name.clone())
+                .or_insert_with(|| TomlValue::Table(IndexMap::new()));
+            table = match value {
+                TomlValue::Table(child) => child,
+                TomlValue::Array(array) if arrays_of_tables.contains(&path[..=index]) => {
+                    match array.last_mut() {
+                        Some(TomlValue::Table(child)) => child,
+                        _ => return Err(Error::new(ErrorKind::TableRedefined(line_number), None)),
+                    }
+                }
+                // The name is already being used by a value that isn't a table
+                _ => {
+                    return Err(DocumentBuilder::duplicate_key_error(
+                        key_lines,
+                        &path[..=index],
+                        line_number,
+                    ))
+                }
+            };
+        }
+        Ok(table)
+    }
+
+    /// Processes a [table] header, all pairs that follow are added to this table
+    pub fn open_table(
+        &mut self,
+        table_name: Vec<Cow<'a, str>>,
+        line_number: usize,
+    ) -> Result<(), Error> {
+        self.define_table(&table_name, line_number)
+            .map_err(|error| error.with_key(&table_name))?;
+        self.current_table = table_name;
+        Ok(())
+    }
+
+    /// Checks that the table can be defined and creates it, the current table is left unchanged
+    fn define_table(
+        &mut self,
+        table_name: &[Cow<'a, str>],
+        line_number: usize,
+    ) -> Result<(), Error> {
+        if self.defined_tables.contains(table_name) || self.arrays_of_tables.contains(table_name) {
+            return Err(Error::new(ErrorKind::TableRedefined(line_number), None));
+        }
+        // A table that was created as the parent of another table can still be defined once
+        DocumentBuilder::get_or_create_table(
+            &mut self.root,
+            table_name,
+            &self.arrays_of_tables,
+            &self.inline_tables,
+            &self.key_lines,
+            line_number,
+        )?;
+        self.defined_tables.insert(table_name.to_vec());
+        self.dotted_tables.clear();
+        self.record_key_line(table_name, line_number);
+        Ok(())
+    }
+
+    /// Processes a [[table]] header, a new table is added to the array and all pairs that follow are added to the new table
+    pub fn open_array_of_tables(
+        &mut self,
+        table_name: Vec<Cow<'a, str>>,
+        line_number: usize,
+    ) -> Result<(), Error> {
+        self.add_array_table(&table_name, line_number)
+            .map_err(|error| error.with_key(&table_name))?;
+        self.current_table = table_name;
+        Ok(())
+    }
+
+    /// Checks that the array of tables can be extended and adds a new table to it, the current table is left unchanged
+    fn add_array_table(
+        &mut self,
+        table_name: &[Cow<'a, str>],
+        line_number: usize,
+    ) -> Result<(), Error> {
+        let (array_name, parent_name) = match table_name.split_last() {
+            Some(split_name) => split_name,
+            None => return Err(Error::new(ErrorKind::InvalidName(line_number), None)),
+        };
+        let parent = DocumentBuilder::get_or_create_table(
+            &mut self.root,
+            parent_name,
+            &self.arrays_of_tables,
+            &self.inline_tables,
+            &self.key_lines,
+            line_number,
+        )?;
+        match parent.entry(array_name.clone()) {
+            Entry::Vacant(entry) => {
+                entry.insert(TomlValue::Array(vec![TomlValue::Table(IndexMap::new())]));
+            }
+            Entry::Occupied(mut entry) => match entry.get_mut() {
+                TomlValue::Array(array) if self.arrays_of_tables.contains(table_name) => {
+                    array.push(TomlValue::Table(IndexMap::new()));
+                }
+                // Static arrays and normal tables can't be extended with a [[table]] header
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidArrayOfTables(line_number),
+                        None,
+                    ))
+                }
+            },
+        }
+        // Any table inside of the previous table in the array is now out of reach, so they can be defined again in the new table
+        let is_nested = |name: &Vec<Cow<'a, str>>| {
+            name.len() > table_name.len() && name.starts_with(table_name)
+        };
+        self.defined_tables.retain(|name| !is_nested(name));
+        self.arrays_of_tables.retain(|name| !is_nested(name));
+        self.inline_tables.retain(|name| !is_nested(name));
+        self.key_lines.retain(|name, _| !is_nested(name));
+        self.arrays_of_tables.insert(table_name.to_vec());
+        self.dotted_tables.clear();
+        self.record_key_line(table_name, line_number);
+        Ok(())
+    }
+
+    /// Adds a name value pair to the current table, a dotted name creates or extends the tables along its path
+    pub fn insert_pair(&mut self, pair: TomlPair<'a>, line_number: usize) -> Result<(), Error> {
+        let TomlPair { name, value } = pair;
+        self.add_value(&name, value, line_number).map_err(|error| {
+            let key: Vec<&Cow<str>> = self.current_table.iter().chain(&name).collect();
+            error.with_key(&key)
+        })
+    }
+
+    /// Adds the value to the current table under the name, the key of any error is set by insert_pair
+    fn add_value(
+        &mut self,
+        pair_name: &[Cow<'a, str>],
+        value: TomlValue<'a>,
+        line_number: usize,
+    ) -> Result<(), Error> {
+        let (name, parent_name) = match pair_name.split_last() {
+            Some(split_name) => split_name,
+            None => return Err(Error::new(ErrorKind::InvalidName(line_number), None)),
+        };
+        let mut table_name = self.current_table.clone();
+        for part in parent_name {
+            table_name.push(part.clone());
+            // A dotted name can only extend tables that were created by other dotted names in the same table
+            if (self.defined_tables.contains(&table_name)
+                && !self.dotted_tables.contains(&table_name))
+                || self.arrays_of_tables.contains(&table_name)
+            {
+                return Err(Error::new(ErrorKind::TableRedefined(line_number), None));
+            }
+        }
+        // The only way a pair can contain a table is if it was defined inline
+        let is_inline_table = matches!(value, TomlValue::Table(_));
+        let table = DocumentBuilder::get_or_create_table(
+            &mut self.root,
+            &table_name,
+            &self.arrays_of_tables,
+            &self.inline_tables,
+            &self.key_lines,
+            line_number,
+        )?;
+        match table.entry(name.clone()) {
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+            }
+            Entry::Occupied(_) => {
+                table_name.push(name.clone());
+                return Err(DocumentBuilder::duplicate_key_error(
+                    &self.key_lines,
+                    &table_name,
+                    line_number,
+                ));
+            }
+        }
+        // The tables along the path are only recorded once the pair has been added, a rejected pair defines nothing
+        for index in self.current_table.len()..table_name.len() {
+            self.dotted_tables.insert(table_name[..=index].to_vec());
+            self.defined_tables.insert(table_name[..=index].to_vec());
+        }
+        table_name.push(name.clone());
+        self.record_key_line(&table_name, line_number);
+        if is_inline_table {
+            self.inline_tables.insert(table_name);
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_empty()
+    }
+
+    pub fn into_table(self) -> TomlTable<'a> {
+        self.root
+    }
+}
+
+impl Document {
+    /// Reads and parses the file at the path, errors are given the path as their file name
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Document, Error> {
+        let file_name = path.as_ref().display().to_string();
+        let data = match std::fs::read(path.as_ref()) {
+            Ok(data) => data,
+            Err(error) => {
+                return Err(
+                    Error::new(ErrorKind::Io, Some(Box::new(error))).with_file_name(file_name)
+                );
+            }
+        };
+        let mut parser = Parser::new();
+        match parser.parse_bytes(&data) {
+            Ok(table) => Ok(Document::from(table)),
+            Err(error) => Err(error.with_file_name(file_name)),
+        }
+    }
+
+    /// The root table of the document
+    pub fn table(&self) -> &TomlTable<'static> {
+        &self.table
+    }
+
+    /// The value with the name in the root table
+    pub fn get(&self, name: &str) -> Option<&TomlValue<'static>> {
+        self.table.get(name)
+    }
+
+    /// The names and values of the root table in the order they appear in the document
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &TomlValue<'static>)> {
+        self.table
+            .iter()
+            .map(|(name, value)| (name.as_ref(), value))
+    }
+
+    /// Sorts the names of every table in the document so that they no longer depend on the order of the document
+    pub fn sort_keys(&mut self) {
+        sort_table(&mut self.table);
+    }
+
+    pub fn into_table(self) -> TomlTable<'static> {
+        self.table
+    }
+}
+
+/// Copies a table that borrows from the text it was parsed from
+impl<'a> From<TomlTable<'a>> for Document {
+    fn from(table: TomlTable<'a>) -> Document {
+        Document {
+            table: into_owned_table(table),
+        }
+    }
+}
+
+impl FromStr for Document {
+    type Err = Error;
+
+    fn from_str(data_to_parse: &str) -> Result<Document, Error> {
+        let mut parser = Parser::new();
+        parser.parse(data_to_parse).map(Document::from)
+    }
+}
@@ -1,30 +1,214 @@
+use std::ops::Range;
+use std::sync::Arc;
+
 use super::{Error, ErrorKind};
 
 impl Error {
-    pub fn new(error_type: ErrorKind, source: Option<Box<dyn std::error::Error>>) -> Error {
+    pub fn new(
+        error_type: ErrorKind,
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Error {
         Error {
             kind: error_type,
-            source,
+            source: source.map(Arc::from),
+            context: Box::default(),
         }
     }
 
     pub fn kind(&self) -> ErrorKind {
-        self.kind
+        self.kind.clone()
+    }
+
+    /// The byte range of the text in the document that caused the error
+    pub fn span(&self) -> Option<Range<usize>> {
+        self.context.span.clone()
+    }
+
+    /// The line that the error starts on, the first line is 1
+    pub fn line_number(&self) -> Option<usize> {
+        self.context.line_number
+    }
+
+    /// The column that the error starts at counted in characters, the first column is 1
+    pub fn column(&self) -> Option<usize> {
+        self.context.column
+    }
+
+    /// The file or other source that the document was read from if it was given with with_file_name
+    pub fn file_name(&self) -> Option<&str> {
+        self.context.file_name.as_deref()
+    }
+
+    /// The dotted name of the table or value that was being read when the error happened, ie server.tls.port
+    pub fn key(&self) -> Option<&str> {
+        self.context.key.as_deref()
+    }
+
+    /// Records the file or other source that the document was read from so that it is shown with the error
+    pub fn with_file_name(mut self, file_name: impl Into<String>) -> Error {
+        self.context.file_name = Some(file_name.into());
+        self
+    }
+
+    /// Sets the name being read when the error happened if it isn't already known
+    pub(crate) fn with_key<S: AsRef<str>>(mut self, key: &[S]) -> Error {
+        if self.context.key.is_none() && !key.is_empty() {
+            self.context.key = Some(format_key(key));
+        }
+        self
+    }
+
+    /// Puts the path to the table that the key is inside of in front of the key, a duplicate name is given the same path
+    /// Names in an inline table are checked before the parser knows where the table is in the document
+    pub(crate) fn with_key_prefix<S: AsRef<str>>(mut self, prefix: &[S]) -> Error {
+        if prefix.is_empty() {
+            return self;
+        }
+        let prefix = format_key(prefix);
+        if let Some(key) = &mut self.context.key {
+            *key = format!("{}.{}", prefix, key);
+        }
+        if let ErrorKind::DuplicateKey { key, .. } = &mut self.kind {
+            *key = format!("{}.{}", prefix, key);
+        }
+        self
+    }
+
+    /// Sets the text that caused the error, the span is only set if a more specific one hasn't already been given
+    pub(crate) fn with_span(mut self, span: Range<usize>) -> Error {
+        if self.context.span.is_none() {
+            self.context.span = Some(span);
+        }
+        self
+    }
+
+    /// Renders the error like a compiler error with the line from the document and the text that caused the error underlined
+    /// The document must be the one that was parsed, ANSI colour codes are added if use_colour is true
+    pub fn render(&self, data_to_parse: &str, use_colour: bool) -> String {
+        let (error_colour, gutter_colour, bold, reset) = match use_colour {
+            true => ("\x1b[1;31m", "\x1b[1;34m", "\x1b[1m", "\x1b[0m"),
+            false => ("", "", "", ""),
+        };
+        let mut report = format!("{}error{}{}: {}{}\n", error_colour, reset, bold, self.kind, reset);
+        let mut gutter = String::from(" ");
+        let mut hint = self.kind.hint().to_string();
+
+        if let (Some(span), Some(line_number), Some(column)) = (self.span(), self.context.line_number, self.context.column) {
+            let line = data_to_parse.lines().nth(line_number - 1).unwrap_or("");
+            gutter = " ".repeat(line_number.to_string().len() + 1);
+            // Control characters are replaced so that they can't change how the report is shown
+            let shown_line: String = line
+                .chars()
+                .map(|char| match char {
+                    '\t' => '\t',
+                    char if char.is_control() => '\u{FFFD}',
+                    char => char,
+                })
+                .collect();
+            // Tabs are kept in the indent so that the underline lines up with the text
+            let indent: String = line
+                .chars()
+                .take(column - 1)
+                .map(|char| if char == '\t' { '\t' } else { ' ' })
+                .collect();
+            // Only the part of the span on the first line is underlined
+            let span_width = data_to_parse
+                .get(span.clone())
+                .unwrap_or("")
+                .chars()
+                .take_while(|char| *char != '\n')
+                .count();
+            let line_width = line.chars().count().saturating_sub(column - 1);
+            let underline = "^".repeat(span_width.min(line_width).max(1));
+
+            let file_name = self.context.file_name.as_ref().map_or(String::new(), |file_name| format!("{}, ", file_name));
+            report.push_str(&format!(
+                "{}{}-->{} {}line {}, column {}\n",
+                &gutter[1..], gutter_colour, reset, file_name, line_number, column
+            ));
+            report.push_str(&format!("{}{}|{}\n", gutter, gutter_colour, reset));
+            report.push_str(&format!(
+                "{}{} |{} {}\n",
+                gutter_colour, line_number, reset, shown_line
+            ));
+            report.push_str(&format!(
+                "{}{}|{} {}{}{}{}\n",
+                gutter, gutter_colour, reset, indent, error_colour, underline, reset
+            ));
+
+            // A value or name that ran on past where it should have ended is usually a string that wasn't closed
+            let before = data_to_parse.get(..span.start).unwrap_or("");
+            if matches!(self.kind, ErrorKind::InvalidValue(_) | ErrorKind::InvalidName(_)) {
+                if let Some(quote) = ["\"\"\"", "'''", "\"", "'"].iter().find(|quote| before.ends_with(*quote)) {
+                    hint = match quote.len() {
+                        3 => format!("multi-line strings must be closed with `{}`", quote),
+                        _ => format!("strings and quoted names must be closed with `{}` on the same line", quote),
+                    };
+                }
+            }
+        }
+
+        report.push_str(&format!(
+            "{}{}={} {}expected{}: {}\n",
+            gutter, gutter_colour, reset, bold, reset, self.kind.expected()
+        ));
+        report.push_str(&format!(
+            "{}{}={} {}hint{}: {}\n",
+            gutter, gutter_colour, reset, bold, reset, hint
+        ));
+        report
     }
+
+    /// Works out the line and column of the span in the document that was parsed
+    /// The line in the kind of error is set to the same line so that they always agree
+    pub(crate) fn locate(mut self, data_to_parse: &str) -> Error {
+        if let Some(span) = self.context.span.as_ref() {
+            let before = data_to_parse.get(..span.start).unwrap_or(data_to_parse);
+            let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+            let line_number = before.matches('\n').count() + 1;
+            self.context.line_number = Some(line_number);
+            self.context.column = Some(before[line_start..].chars().count() + 1);
+            self.kind.set_line_number(line_number);
+        }
+        self
+    }
+}
+
+/// Joins the parts of a name with dots, parts that aren't bare names are quoted so that "a.b" can't be confused with a.b
+pub(crate) fn format_key<S: AsRef<str>>(key: &[S]) -> String {
+    let key: Vec<String> = key
+        .iter()
+        .map(|part| {
+            let part = part.as_ref();
+            let is_bare = !part.is_empty()
+                && part
+                    .chars()
+                    .all(|char| char.is_ascii_alphanumeric() || char == '_' || char == '-');
+            match is_bare {
+                true => part.to_string(),
+                false => format!("\"{}\"", part.replace('\\', "\\\\").replace('"', "\\\"")),
+            }
+        })
+        .collect();
+    key.join(".")
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Error in TOML Parser")?;
+        if let Some(file_name) = self.context.file_name.as_ref() {
+            f.write_fmt(format_args!(" in {}", file_name))?;
+        }
+        if let Some(key) = self.context.key.as_ref() {
+            f.write_fmt(format_args!(" at {}", key))?;
+        }
         if let Some(underlying_error) = self.source.as_ref() {
             f.write_fmt(format_args!(
-                "Error in TOML Parser, error was {}, underlying error was {}",
+                ", error was {}, underlying error was {}",
                 self.kind, underlying_error
             ))?;
         } else {
-            f.write_fmt(format_args!(
-                "Error in TOML Parser, error was {}",
-                self.kind
-            ))?;
+            f.write_fmt(format_args!(", error was {}", self.kind))?;
         }
         Ok(())
     }
@@ -32,7 +216,21 @@ impl std::fmt::Display for Error {
 
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        None
+        self.source
+            .as_ref()
+            .map(|source| source.as_ref() as &(dyn std::error::Error + 'static))
+    }
+}
+
+/// Errors are equal if they are the same kind at the same place, the underlying errors can only be compared by their message
+impl PartialEq for Error {
+    fn eq(&self, other: &Error) -> bool {
+        let same_source = match (self.source.as_ref(), other.source.as_ref()) {
+            (Some(source), Some(other_source)) => source.to_string() == other_source.to_string(),
+            (None, None) => true,
+            _ => false,
+        };
+        self.kind == other.kind && self.context == other.context && same_source
     }
 }
 
@@ -45,6 +243,114 @@ impl std::fmt::Display for ErrorKind {
             ErrorKind::UnknownValueType(line_number) => f.write_fmt(format_args!("The value on line {} is of an unknown type", line_number)),
             ErrorKind::InvalidValue(line_number) => f.write_fmt(format_args!("The value on line {} is invalid", line_number)),
             ErrorKind::InvalidName(line_number) => f.write_fmt(format_args!("The name on line {} is invalid", line_number)),
+            ErrorKind::InvalidComment(line_number) => f.write_fmt(format_args!("The comment on line {} contains a control character", line_number)),
+            ErrorKind::TableRedefined(line_number) => f.write_fmt(format_args!("The table on line {} has already been defined", line_number)),
+            ErrorKind::InvalidArrayOfTables(line_number) => f.write_fmt(format_args!("The array of tables on line {} names a key that is not an array of tables", line_number)),
+            ErrorKind::UnterminatedArray(line_number) => f.write_fmt(format_args!("The array starting on line {} was never closed", line_number)),
+            ErrorKind::InvalidInlineTable(line_number) => f.write_fmt(format_args!("The inline table on line {} must be closed on the same line and can't end with a comma", line_number)),
+            ErrorKind::DuplicateKey { key, line_number, first_line_number } => f.write_fmt(format_args!("The name {} on line {} was already given a value on line {}", key, line_number, first_line_number)),
+            ErrorKind::InvalidEscape(line_number) => f.write_fmt(format_args!("The string on line {} contains an invalid escape sequence", line_number)),
+            ErrorKind::InvalidDateTime(line_number) => f.write_fmt(format_args!("The date or time on line {} is invalid", line_number)),
+            ErrorKind::IntegerOverflow(line_number) => f.write_fmt(format_args!("The integer on line {} does not fit in a 64 bit signed integer", line_number)),
+            ErrorKind::InvalidUnderscore(line_number) => f.write_fmt(format_args!("The number on line {} has an underscore that is not between two digits", line_number)),
+            ErrorKind::LeadingZero(line_number) => f.write_fmt(format_args!("The number on line {} has a leading zero", line_number)),
+            ErrorKind::InvalidSign(line_number) => f.write_fmt(format_args!("The number on line {} has a sign but only decimal numbers can have a single sign", line_number)),
+            ErrorKind::InvalidPrefix(line_number) => f.write_fmt(format_args!("The integer on line {} has an uppercase prefix, only 0x, 0o and 0b are allowed", line_number)),
+            ErrorKind::InvalidFloat(line_number) => f.write_fmt(format_args!("The float on line {} must have digits on both sides of the decimal point and in the exponent", line_number)),
+            ErrorKind::InvalidBareKey(line_number) => f.write_fmt(format_args!("The name on line {} can only contain A-Z, a-z, 0-9, _ and - unless it is quoted", line_number)),
+            ErrorKind::InvalidControlCharacter(line_number) => f.write_fmt(format_args!("The string on line {} contains a control character that must be escaped", line_number)),
+            ErrorKind::InvalidUtf8(line_number) => f.write_fmt(format_args!("Line {} is not valid UTF-8", line_number)),
+            ErrorKind::Io => f.write_str("The document could not be read"),
+        }
+    }
+}
+
+impl ErrorKind {
+    fn set_line_number(&mut self, line_number: usize) {
+        match self {
+            ErrorKind::InvalidEndOfLine(line)
+            | ErrorKind::MissingValue(line)
+            | ErrorKind::UnknownValueType(line)
+            | ErrorKind::InvalidValue(line)
+            | ErrorKind::InvalidName(line)
+            | ErrorKind::InvalidComment(line)
+            | ErrorKind::TableRedefined(line)
+            | ErrorKind::InvalidArrayOfTables(line)
+            | ErrorKind::UnterminatedArray(line)
+            | ErrorKind::InvalidInlineTable(line)
+            | ErrorKind::DuplicateKey {
+                line_number: line, ..
+            }
+            | ErrorKind::InvalidEscape(line)
+            | ErrorKind::InvalidDateTime(line)
+            | ErrorKind::IntegerOverflow(line)
+            | ErrorKind::InvalidUnderscore(line)
+            | ErrorKind::LeadingZero(line)
+            | ErrorKind::InvalidSign(line)
+            | ErrorKind::InvalidPrefix(line)
+            | ErrorKind::InvalidFloat(line)
+            | ErrorKind::InvalidBareKey(line)
+            | ErrorKind::InvalidControlCharacter(line)
+            | ErrorKind::InvalidUtf8(line) => *line = line_number,
+            ErrorKind::Io => {}
+        }
+    }
+
+    /// Describes what the parser expected to find instead of the text that caused the error
+    fn expected(&self) -> &'static str {
+        match self {
+            ErrorKind::InvalidEndOfLine(_) => "each line to end with \\n or \\r\\n",
+            ErrorKind::MissingValue(_) => "a value after the =",
+            ErrorKind::UnknownValueType(_) => "a string, number, boolean, date, time, array or inline table",
+            ErrorKind::InvalidValue(_) => "a value followed by the end of the line or a comment",
+            ErrorKind::InvalidName(_) => "a name followed by =, names can't be empty or span several lines",
+            ErrorKind::InvalidComment(_) => "a comment without control characters other than tab",
+            ErrorKind::TableRedefined(_) => "each table to only be defined once",
+            ErrorKind::InvalidArrayOfTables(_) => "a name that is only used for an array of tables",
+            ErrorKind::UnterminatedArray(_) => "a ] to close the array",
+            ErrorKind::InvalidInlineTable(_) => "name value pairs separated by commas and a } on the same line",
+            ErrorKind::DuplicateKey { .. } => "each name to only be given a value once",
+            ErrorKind::InvalidEscape(_) => "one of the escapes \\b \\t \\n \\f \\r \\\" \\\\ \\uXXXX or \\UXXXXXXXX",
+            ErrorKind::InvalidDateTime(_) => "a date like 1979-05-27, a time like 07:32:00 or both separated by a T",
+            ErrorKind::IntegerOverflow(_) => "an integer from -9223372036854775808 to 9223372036854775807",
+            ErrorKind::InvalidUnderscore(_) => "each underscore in a number to be between two digits",
+            ErrorKind::LeadingZero(_) => "a number that doesn't start with 0 unless it is 0",
+            ErrorKind::InvalidSign(_) => "a sign only at the start of a decimal number",
+            ErrorKind::InvalidPrefix(_) => "one of the prefixes 0x, 0o or 0b",
+            ErrorKind::InvalidFloat(_) => "digits on both sides of the decimal point and after the exponent",
+            ErrorKind::InvalidBareKey(_) => "a name made of A-Z, a-z, 0-9, _ and -",
+            ErrorKind::InvalidControlCharacter(_) => "a string without control characters other than tab",
+            ErrorKind::InvalidUtf8(_) => "the document to be UTF-8 text",
+            ErrorKind::Io => "a file that exists and can be read",
+        }
+    }
+
+    /// Suggests how the document could be fixed
+    fn hint(&self) -> &'static str {
+        match self {
+            ErrorKind::InvalidEndOfLine(_) => "a \\r on its own is not a line ending",
+            ErrorKind::MissingValue(_) => "use \"\" for an empty string",
+            ErrorKind::UnknownValueType(_) => "strings must be quoted, ie name = \"value\"",
+            ErrorKind::InvalidValue(_) => "each name value pair must be on its own line",
+            ErrorKind::InvalidName(_) => "quote the name if it needs spaces or dots, ie \"my name\" = 1",
+            ErrorKind::InvalidComment(_) => "remove the control character from the comment",
+            ErrorKind::TableRedefined(_) => "add the values to the first definition of the table",
+            ErrorKind::InvalidArrayOfTables(_) => "[[name]] can only add to names that were created by [[name]]",
+            ErrorKind::UnterminatedArray(_) => "close the array with a ] after the last value",
+            ErrorKind::InvalidInlineTable(_) => "use a [table] header for a table that spans several lines",
+            ErrorKind::DuplicateKey { .. } => "remove one of the values or give it a different name",
+            ErrorKind::InvalidEscape(_) => "use a literal string to avoid escapes, ie 'C:\\path'",
+            ErrorKind::InvalidDateTime(_) => "check that the date exists and each part has the right number of digits",
+            ErrorKind::IntegerOverflow(_) => "use a float or a string for larger numbers",
+            ErrorKind::InvalidUnderscore(_) => "remove the underscore or put a digit on both sides of it",
+            ErrorKind::LeadingZero(_) => "use the 0o prefix for octal numbers",
+            ErrorKind::InvalidSign(_) => "remove the sign or write the number in decimal",
+            ErrorKind::InvalidPrefix(_) => "prefixes must be lowercase",
+            ErrorKind::InvalidFloat(_) => "write 1.0 rather than 1. and 0.5 rather than .5",
+            ErrorKind::InvalidBareKey(_) => "quote the name to use other characters, ie \"naïve\" = 1",
+            ErrorKind::InvalidControlCharacter(_) => "escape the character in a basic string, ie \\u0007",
+            ErrorKind::InvalidUtf8(_) => "save the document as UTF-8",
+            ErrorKind::Io => "check the path and the permissions of the file",
         }
     }
 }
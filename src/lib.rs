@@ -1,6 +1,29 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+
+/// Parser tracing is forwarded to the log crate when the log feature is enabled and compiled out otherwise
+#[cfg(feature = "log")]
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        log::trace!($($arg)*)
+    };
+}
+
+#[cfg(not(feature = "log"))]
+macro_rules! trace {
+    ($($arg:tt)*) => {};
+}
+
+mod datetime;
+mod document;
 mod error;
 mod pair;
 mod parser;
+mod value;
 
 pub struct Parser {
     position: usize,
@@ -10,31 +33,161 @@ pub struct Parser {
     name_end: Option<usize>,
     value_start: Option<usize>,
     value_end: Option<usize>,
+    /// Each part of a dotted name
+    name_parts: Vec<NamePart>,
+    /// Start of the name or value being read, an error while reading it covers everything from here
+    token_start: Option<usize>,
+    /// The text of the last name that was built, errors from adding the name to the document cover it
+    name_span: Range<usize>,
+    /// The line the name being read started on, a value can end on a later line
+    name_line: usize,
+    /// Each part of the last name that was built, errors in the text after its value are about it
+    built_name_parts: Vec<NamePart>,
 }
 
-// Currently only supports strings and integers
-#[derive(PartialEq, Debug)]
+/// Start and end of one part of a dotted name and if it was a basic string that can contain escapes
+pub(crate) type NamePart = (usize, usize, bool);
+
+/// A TOML table, the root of a document is also a table
+/// Names only need to be copied from the document if they contain escapes, ie "tab\tseperated"
+/// Names are kept in the order they were first given in the document, tables are equal if they have the same names and values in any order
+pub type TomlTable<'a> = IndexMap<Cow<'a, str>, TomlValue<'a>>;
+
+#[derive(PartialEq, Debug, Clone)]
 pub enum TomlValue<'a> {
-    String(&'a str), // TODO: Strings are subject to more restrictions than a UTF8 string
+    String(Cow<'a, str>), // Only copied from the document if the string contains escapes
     Integer(i64),
     Float(f64),
     Bool(bool),
+    OffsetDateTime(OffsetDateTime),
+    LocalDateTime(LocalDateTime),
+    LocalDate(LocalDate),
+    LocalTime(LocalTime),
+    Array(Vec<TomlValue<'a>>),
+    Table(TomlTable<'a>),
+}
+
+/// A parsed document that owns its names and strings so that it can be kept after the text it was parsed from is dropped
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Document {
+    table: TomlTable<'static>,
+}
+
+/// A calendar date without a time or offset, ie 1979-05-27
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct LocalDate {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+/// A time of day without a date or offset, ie 07:32:00.999999
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct LocalTime {
+    pub hour: u8,
+    pub minute: u8,
+    /// 60 is a leap second
+    pub second: u8,
+    /// Fractional seconds, any precision beyond nanoseconds is truncated
+    pub nanosecond: u32,
+}
+
+/// A date and time without an offset, ie 1979-05-27T07:32:00
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct LocalDateTime {
+    pub date: LocalDate,
+    pub time: LocalTime,
+}
+
+/// A date and time with an offset from UTC, ie 1979-05-27T07:32:00-08:00
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct OffsetDateTime {
+    pub date: LocalDate,
+    pub time: LocalTime,
+    /// Offset from UTC in minutes, Z is an offset of 0
+    pub offset: i16,
 }
 
-// TODO: A TOML name can be a name or a String
 #[derive(PartialEq, Debug)]
 pub struct TomlPair<'a> {
-    name: &'a str, // TODO: Two types of names, normal and string
+    name: Vec<Cow<'a, str>>, // Each part of a dotted name, quoted parts have had their escapes decoded
     value: TomlValue<'a>,
 }
 
-#[derive(Debug)]
+/// A single statement read from a TOML document
+#[derive(PartialEq, Debug)]
+pub(crate) enum TomlStatement<'a> {
+    /// A name value pair
+    Pair(TomlPair<'a>),
+    /// A [table] header, contains each part of the dotted table name
+    Table(Vec<Cow<'a, str>>),
+    /// A [[table]] header, adds a new table to an array of tables
+    ArrayOfTables(Vec<Cow<'a, str>>),
+}
+
+/// A value that has been started but not finished, these are kept on a stack so that values can be nested
+pub(crate) enum OpenValue<'a> {
+    Array {
+        /// The values read so far
+        values: Vec<TomlValue<'a>>,
+        /// The line the array started on
+        line_number: usize,
+        /// The index of the [ that started the array
+        start: usize,
+        /// Each part of the name the array will be given once it is finished, arrays inside of arrays have no name
+        name: Vec<NamePart>,
+    },
+    InlineTable {
+        /// The pairs read so far
+        table: Box<DocumentBuilder<'a>>,
+        /// The line the inline table started on
+        line_number: usize,
+        /// Each part of the name the table will be given once it is finished, tables inside of arrays have no name
+        name: Vec<NamePart>,
+    },
+}
+
+/// Builds the nested tables of a document from the statements read by the parser
+pub(crate) struct DocumentBuilder<'a> {
+    root: TomlTable<'a>,
+    /// The table that name value pairs are currently being added to
+    current_table: Vec<Cow<'a, str>>,
+    /// Tables that have been defined with a header or a dotted name, a table can only be defined once
+    defined_tables: HashSet<Vec<Cow<'a, str>>>,
+    /// Tables that were defined by a dotted name in the current table, these can be extended by other dotted names until the next header
+    dotted_tables: HashSet<Vec<Cow<'a, str>>>,
+    /// Arrays that were created by a [[table]] header, only these arrays can have tables appended
+    arrays_of_tables: HashSet<Vec<Cow<'a, str>>>,
+    /// Tables that were defined inline, an inline table can't be extended once it has been defined
+    inline_tables: HashSet<Vec<Cow<'a, str>>>,
+    /// The line each name and table was first defined on, used to report duplicate keys
+    key_lines: HashMap<Vec<Cow<'a, str>>, usize>,
+}
+
+#[derive(Debug, Clone)]
 pub struct Error {
     kind: ErrorKind,
-    source: Option<Box<dyn std::error::Error>>,
+    /// Shared so that the error can be cloned, it is Send and Sync so that the error can be sent between threads
+    source: Option<Arc<dyn std::error::Error + Send + Sync>>,
+    /// Boxed so that results holding an error stay small
+    context: Box<ErrorContext>,
+}
+
+/// Where an error happened and what was being read
+#[derive(Debug, Clone, PartialEq, Default)]
+struct ErrorContext {
+    /// Byte range of the text that caused the error, only known for errors from parsing a document
+    span: Option<Range<usize>>,
+    /// Line and column that the span starts at, both start from 1
+    line_number: Option<usize>,
+    column: Option<usize>,
+    /// The file or other source the document was read from, set by the caller
+    file_name: Option<String>,
+    /// The dotted name of the table or value that was being read, ie server.tls.port
+    key: Option<String>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ErrorKind {
     /// There are only two valid line endings \n and \r\n
     InvalidEndOfLine(usize),
@@ -46,29 +199,102 @@ pub enum ErrorKind {
     InvalidValue(usize),
     /// Name contains invalid characters, ie fred\n = 4 or fred \n = 4
     InvalidName(usize),
+    /// Comment contains a control character other than tab
+    InvalidComment(usize),
+    /// A `[table]` header names a table that has already been defined, an inline table or a key that is not a table
+    TableRedefined(usize),
+    /// A `[[table]]` header names a key that is not an array of tables
+    InvalidArrayOfTables(usize),
+    /// File ended before an array was closed with a ], contains the line the array started on
+    UnterminatedArray(usize),
+    /// Inline table was not closed on the line it started on or contained a trailing comma
+    InvalidInlineTable(usize),
+    /// A name has already been given a value, ie fred = 1 followed by fred = 2 or fred.age = 2
+    DuplicateKey {
+        /// The full dotted name, ie server.port
+        key: String,
+        /// The line the name was given a value again
+        line_number: usize,
+        /// The line the name was first given a value
+        first_line_number: usize,
+    },
+    /// A basic string contains an unknown escape or a \u escape that is not a unicode scalar value
+    InvalidEscape(usize),
+    /// A date or time is malformed or out of range, ie 1979-02-30
+    InvalidDateTime(usize),
+    /// An integer is too large or too small to fit in an i64
+    IntegerOverflow(usize),
+    /// An underscore in a number is not between two digits, ie 1__000, _1, 1_ or 0x_1
+    InvalidUnderscore(usize),
+    /// A decimal integer or float starts with a zero, ie 0123
+    LeadingZero(usize),
+    /// A sign is used on a hex, octal or binary integer or repeated, ie +0x10 or +-1
+    InvalidSign(usize),
+    /// A hex, octal or binary prefix is uppercase, ie 0X10
+    InvalidPrefix(usize),
+    /// A float is missing the digits before or after the decimal point or in the exponent, ie 1. or .5 or 1e
+    InvalidFloat(usize),
+    /// A name that is not quoted contains a character other than A-Z, a-z, 0-9, _ and -
+    InvalidBareKey(usize),
+    /// A string or quoted name contains a control character other than tab, these must be escaped
+    InvalidControlCharacter(usize),
+    /// The document is not valid UTF-8
+    InvalidUtf8(usize),
+    /// The document couldn't be read from a file, the IO error is the source of the error
+    Io,
 }
 
 pub enum ParserState {
     /// Parser expects to see a name, whitespace or the end of the file
     Normal,
+    /// Parser has seen a [ or a . in a table header and expects the next part of the table name
+    BeforeTableName(
+        /// Is this a `[[table]]` header
+        bool,
+    ),
+    /// Parser is reading part of a table name
+    ReadingTableName(
+        /// Is this a `[[table]]` header
+        bool,
+    ),
+    /// Parser has read part of a table name and expects a . or a ]
+    AfterTableName(
+        /// Is this a `[[table]]` header
+        bool,
+    ),
+    /// Parser has finished reading a table header
+    FinishedTable(
+        /// Is this a `[[table]]` header
+        bool,
+    ),
     /// Parser has started reading a name
     ReadingName,
+    /// Parser has seen a . in a dotted name and expects the next part of the name
+    BeforeNamePart,
     /// Parser has read a name and now expects an =
     BeforeEquals,
     /// Parser has seen an = and is now expecting a value of some kind
     AfterEquals,
+    /// Parser has found the first character of a value
+    StartOfValue,
+    /// Parser is inside an array and expects a value or the end of the array
+    BeforeArrayValue,
+    /// Parser has read a value inside an array and expects a comma or the end of the array
+    AfterArrayValue,
+    /// Parser is inside an inline table and expects a name or the end of the table
+    BeforeInlineTableName,
+    /// Parser has read a pair inside an inline table and expects a comma or the end of the table
+    AfterInlineTableValue,
     /// Parser is reading a basic "hello" string
     ReadingString,
-    /// Parser is reading an integer or potentially a float or date, if it is an integer then it is base 10
+    /// Parser is reading a literal 'C:\Users' string, there are no escapes in a literal string
+    ReadingLiteralString,
+    /// Parser is reading a """multi-line""" string, the bool is true for a basic string and false for a literal string
+    ReadingMultiLineString(bool),
+    /// Parser is reading a date, a time or a date and time, ie 1979-05-27T07:32:00Z, contains the start index of the value
+    ReadingDateTime(usize),
+    /// Parser is reading an integer or potentially a float, if it is an integer then it is base 10 and may be signed
     ReadingInteger,
-    // TODO: Change this so that it is passed as state rather than a totally seperate state
-    ReadingNegativeInteger,
-    FinishedNegativeInteger(
-        /// End of file Reached
-        bool,
-        /// Finished on a new line
-        bool,
-    ),
     ReadingOctalInteger,
     ReadingHexInteger,
     ReadingBinaryInteger,
@@ -81,40 +307,26 @@ pub enum ParserState {
     ReadingTrue(usize),
     /// Parser is reading a false boolean
     ReadingFalse(usize),
+    /// Parser is reading inf or nan with an optional sign, contains the start index of the value
+    ReadingSpecialFloat(usize),
     /// Parser has finished reading a value and is waiting until it sees a next line
     AfterValue,
     /// Parser has finished reading a String value
     FinishedString,
+    /// Parser has finished reading a literal String value
+    FinishedLiteralString,
+    /// Parser has finished reading a date, a time or a date and time
+    FinishedDateTime,
     /// Parser has finished reading a float value
-    FinishedFloat(bool, bool),
+    FinishedFloat,
     /// Parser has finished reading a integer value
-    FinishedInteger(
-        /// End of file Reached
-        bool,
-        /// Finished on a new line
-        bool,
-    ),
-    /// Parser finished reading a binary integer value
-    FinishedBinaryInteger(
-        /// End of file reached
-        bool,
-        /// Finished on a new line
-        bool,
-    ),
-    /// Parser finished reading a binary integer value
-    FinishedHexInteger(
-        /// End of file reached
-        bool,
-        /// Finished on a new line
-        bool,
-    ),
+    FinishedInteger,
     /// Parser finished reading a binary integer value
-    FinishedOctalInteger(
-        /// End of file reached
-        bool,
-        /// Finished on a new line
-        bool,
-    ),
+    FinishedBinaryInteger,
+    /// Parser finished reading a hex integer value
+    FinishedHexInteger,
+    /// Parser finished reading an octal integer value
+    FinishedOctalInteger,
     FinishedBoolean(bool), // Parser has finished reading a boolean value
     EndOfFile,             // Parser has reached the end of a file
 }
@@ -130,7 +342,7 @@ mod test_parser {
         let pair = parser.read_test_pair(toml_string).unwrap();
         assert_eq!(
             pair,
-            Some(TomlPair::new("junk", TomlValue::String("caveman")))
+            Some(TomlPair::new("junk", TomlValue::String("caveman".into())))
         );
     }
 
@@ -141,7 +353,7 @@ mod test_parser {
         let pair = parser.read_test_pair(toml_string).unwrap();
         assert_eq!(
             pair,
-            Some(TomlPair::new("junk", TomlValue::String("caveman")))
+            Some(TomlPair::new("junk", TomlValue::String("caveman".into())))
         );
     }
 
@@ -154,7 +366,7 @@ mod test_parser {
         let pair = parser.read_test_pair(toml_string).unwrap();
         assert_eq!(
             pair,
-            Some(TomlPair::new("junk", TomlValue::String("caveman")))
+            Some(TomlPair::new("junk", TomlValue::String("caveman".into())))
         );
         let second_pair = parser.read_test_pair(toml_string).unwrap();
         assert_eq!(second_pair, None);
@@ -166,12 +378,12 @@ mod test_parser {
         let pair = parser.read_test_pair(toml_string).unwrap();
         assert_eq!(
             pair,
-            Some(TomlPair::new("junk", TomlValue::String("caveman")))
+            Some(TomlPair::new("junk", TomlValue::String("caveman".into())))
         );
         let second_pair = parser.read_test_pair(toml_string).unwrap();
         assert_eq!(
             second_pair,
-            Some(TomlPair::new("aggro", TomlValue::String("fred")))
+            Some(TomlPair::new("aggro", TomlValue::String("fred".into())))
         );
     }
 
@@ -182,18 +394,146 @@ mod test_parser {
         let pair = parser.read_test_pair(toml_string).unwrap();
         assert_eq!(
             pair,
-            Some(TomlPair::new("junk", TomlValue::String("caveman"))),
+            Some(TomlPair::new("junk", TomlValue::String("caveman".into()))),
             "First test failed"
         );
         let second_pair = parser.read_test_pair(toml_string).unwrap();
         assert_eq!(
             second_pair,
-            Some(TomlPair::new("aggro", TomlValue::String("fred"))),
+            Some(TomlPair::new("aggro", TomlValue::String("fred".into()))),
             "Second test failed"
         );
         assert_eq!(parser.read_test_pair(toml_string).unwrap(), None);
     }
 
+    #[test]
+    fn test_string_escapes() {
+        let toml_string = "quote = \"say \\\"hi\\\"\"\nall = \"\\b\\t\\n\\f\\r\\\\\\u00E9\\U0001F600\"\nplain = \"no escapes\"";
+        let mut parser = super::Parser::new();
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert_eq!(pair.value(), &TomlValue::String("say \"hi\"".into()));
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert_eq!(
+            pair.value(),
+            &TomlValue::String("\u{8}\t\n\u{C}\r\\\u{E9}\u{1F600}".into())
+        );
+        // A string is only copied if it contains an escape
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert!(matches!(pair.value(), TomlValue::String(Cow::Borrowed("no escapes"))));
+    }
+
+    #[test]
+    fn test_literal_string() {
+        let toml_string = "path = 'C:\\Users\\nodejs\\templates'\nregex = '<\\i\\c*\\s*>' # Comment\nquoted = 'Tom \"Dubs\" Preston-Werner'\nempty = ''\nlist = ['a', \"b\"]";
+        let mut parser = super::Parser::new();
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert!(matches!(
+            pair.value(),
+            TomlValue::String(Cow::Borrowed("C:\\Users\\nodejs\\templates"))
+        ));
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert_eq!(pair.value(), &TomlValue::String("<\\i\\c*\\s*>".into()));
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert_eq!(pair.value(), &TomlValue::String("Tom \"Dubs\" Preston-Werner".into()));
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert_eq!(pair.value(), &TomlValue::String("".into()));
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert_eq!(
+            pair.value(),
+            &TomlValue::Array(vec![TomlValue::String("a".into()), TomlValue::String("b".into())])
+        );
+
+        let invalid_strings = ["a = 'not closed", "a = 'new\nline'", "a = 'bell\u{7}'", "a = 'it's'"];
+        for toml_string in invalid_strings.iter() {
+            let mut parser = super::Parser::new();
+            assert!(parser.parse(toml_string).is_err(), "{} should not parse", toml_string);
+        }
+    }
+
+    #[test]
+    fn test_multi_line_string() {
+        let toml_string = "sql = \"\"\"\nSELECT *\n  FROM \"users\"\"\"\"\nnext = 1";
+        let mut parser = super::Parser::new();
+        let document = parser.parse(toml_string).unwrap();
+        assert_eq!(
+            document.get("sql"),
+            Some(&TomlValue::String("SELECT *\n  FROM \"users\"".into()))
+        );
+        assert_eq!(document.get("next"), Some(&TomlValue::Integer(1)));
+
+        let toml_string2 = "a = \"\"\"\"\"\"\nb = \"\"\"\"\"quoted\"\"\"\"\"\nc = \"\"\"\r\nwindows\r\nline\"\"\"";
+        let mut parser2 = super::Parser::new();
+        let document2 = parser2.parse(toml_string2).unwrap();
+        assert_eq!(document2.get("a"), Some(&TomlValue::String("".into())));
+        assert_eq!(document2.get("b"), Some(&TomlValue::String("\"\"quoted\"\"".into())));
+        assert_eq!(document2.get("c"), Some(&TomlValue::String("windows\r\nline".into())));
+    }
+
+    #[test]
+    fn test_multi_line_string_line_ending_backslash() {
+        let toml_string = "str = \"\"\"\nThe quick brown \\\n\n\n  fox jumps over \\   \n    the lazy dog.\\n\"\"\"\nline = 7";
+        let mut parser = super::Parser::new();
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert_eq!(
+            pair.value(),
+            &TomlValue::String("The quick brown fox jumps over the lazy dog.\n".into())
+        );
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert_eq!(pair.value(), &TomlValue::Integer(7));
+        assert_eq!(parser.line_number, 7);
+    }
+
+    #[test]
+    fn test_multi_line_literal_string() {
+        let toml_string = "regex = '''I [dw]on't need \\d{2} apples'''\nlines = '''\nThe first newline is\ntrimmed in raw strings.\n   \\ All other whitespace\n   is preserved.\n'''\nquotes = ''''That,' she said, 'is still pointless.''''";
+        let mut parser = super::Parser::new();
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert!(matches!(
+            pair.value(),
+            TomlValue::String(Cow::Borrowed("I [dw]on't need \\d{2} apples"))
+        ));
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert_eq!(
+            pair.value(),
+            &TomlValue::String("The first newline is\ntrimmed in raw strings.\n   \\ All other whitespace\n   is preserved.\n".into())
+        );
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert_eq!(
+            pair.value(),
+            &TomlValue::String("'That,' she said, 'is still pointless.'".into())
+        );
+    }
+
+    #[test]
+    fn test_invalid_multi_line_string() {
+        let invalid_strings = ["a = \"\"\"not closed\"\"", "a = '''not closed", "a = \"\"\"too many quotes\"\"\"\"\"\"", "a = '''bell\u{7}'''", "a = \"\"\"\\ x\"\"\"", "a = \"\"\"cr\rline\"\"\""];
+        for toml_string in invalid_strings.iter() {
+            let mut parser = super::Parser::new();
+            assert!(parser.parse(toml_string).is_err(), "{} should not parse", toml_string);
+        }
+    }
+
+    #[test]
+    fn test_invalid_string_escapes() {
+        let invalid_escapes = ["a = \"\\x41\"", "a = \"\\u12\"", "a = \"\\u12G4\"", "a = \"\\uDFFF\"", "a = \"\\U00110000\"", "a = \"\\ \"", "b = 1\na = \"\\e\""];
+        for toml_string in invalid_escapes.iter() {
+            let mut parser = super::Parser::new();
+            let error = parser.parse(toml_string).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidEscape(toml_string.lines().count()), "{}", toml_string);
+        }
+
+        let invalid_strings = ["a = \"ends with escape\\\"", "a = \"\\\n\""];
+        for toml_string in invalid_strings.iter() {
+            let mut parser = super::Parser::new();
+            let error = parser.parse(toml_string).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidValue(1), "{}", toml_string);
+        }
+
+        let toml_string = "a = \"tab\tis allowed\"";
+        let mut parser = super::Parser::new();
+        assert!(parser.parse(toml_string).is_ok());
+    }
+
     #[test]
     fn test_reading_integer() {
         let toml_string = "junk = 1234";
@@ -415,7 +755,7 @@ mod test_parser {
         // Reading a float will panic for now
         let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
 
-        assert_eq!(pair.name, "float", "Failed first test");
+        assert_eq!(pair.name, vec!["float"], "Failed first test");
         let value = match pair.value {
             TomlValue::Float(d) => d,
             _ => unreachable!(
@@ -441,6 +781,161 @@ mod test_parser {
         );
     }
 
+    #[test]
+    fn test_reading_number_grammar() {
+        let valid_numbers = [
+            ("1_000", TomlValue::Integer(1000)),
+            ("5_349_221", TomlValue::Integer(5_349_221)),
+            ("+99", TomlValue::Integer(99)),
+            ("-17", TomlValue::Integer(-17)),
+            ("+0", TomlValue::Integer(0)),
+            ("-0", TomlValue::Integer(0)),
+            ("0xdead_beef", TomlValue::Integer(0xdead_beef)),
+            ("0o01_234", TomlValue::Integer(0o1234)),
+            ("0b1101_0110", TomlValue::Integer(0b1101_0110)),
+            ("+1.0", TomlValue::Float(1.0)),
+            ("-3.25", TomlValue::Float(-3.25)),
+            ("-0.01", TomlValue::Float(-0.01)),
+            ("5e+22", TomlValue::Float(5e22)),
+            ("1e06", TomlValue::Float(1e6)),
+            ("-2E-2", TomlValue::Float(-2e-2)),
+            ("6.626e-34", TomlValue::Float(6.626e-34)),
+            ("224_617.445_991_228", TomlValue::Float(224_617.445_991_228)),
+            ("0e0", TomlValue::Float(0.0)),
+            ("-0.0", TomlValue::Float(-0.0)),
+        ];
+        for (number, value) in valid_numbers.iter() {
+            let toml_string = format!("number = {}", number);
+            let mut parser = super::Parser::new();
+            let pair = parser.read_test_pair(&toml_string).unwrap().unwrap();
+            assert_eq!(pair.value(), value, "{}", number);
+        }
+    }
+
+    #[test]
+    fn test_reading_invalid_number_grammar() {
+        let invalid_numbers = [
+            ("1__2", ErrorKind::InvalidUnderscore(2)),
+            ("_1", ErrorKind::InvalidUnderscore(2)),
+            ("1_", ErrorKind::InvalidUnderscore(2)),
+            ("-_1", ErrorKind::InvalidUnderscore(2)),
+            ("0x_1", ErrorKind::InvalidUnderscore(2)),
+            ("0x1_", ErrorKind::InvalidUnderscore(2)),
+            ("0b1__0", ErrorKind::InvalidUnderscore(2)),
+            ("1._5", ErrorKind::InvalidFloat(2)),
+            ("1_.5", ErrorKind::InvalidUnderscore(2)),
+            ("1e_5", ErrorKind::InvalidFloat(2)),
+            ("0123", ErrorKind::LeadingZero(2)),
+            ("-0123", ErrorKind::LeadingZero(2)),
+            ("00.5", ErrorKind::LeadingZero(2)),
+            ("0_1", ErrorKind::LeadingZero(2)),
+            ("+0x10", ErrorKind::InvalidSign(2)),
+            ("-0o7", ErrorKind::InvalidSign(2)),
+            ("+0b1", ErrorKind::InvalidSign(2)),
+            ("+-1", ErrorKind::InvalidSign(2)),
+            ("--1", ErrorKind::InvalidSign(2)),
+            ("0X10", ErrorKind::InvalidPrefix(2)),
+            ("0O7", ErrorKind::InvalidPrefix(2)),
+            ("0B1", ErrorKind::InvalidPrefix(2)),
+            ("1.", ErrorKind::InvalidFloat(2)),
+            ("1. ", ErrorKind::InvalidFloat(2)),
+            ("1.e5", ErrorKind::InvalidFloat(2)),
+            (".5", ErrorKind::InvalidFloat(2)),
+            ("-.5", ErrorKind::InvalidFloat(2)),
+            ("1e", ErrorKind::InvalidFloat(2)),
+            ("1e+", ErrorKind::InvalidFloat(2)),
+            ("0.", ErrorKind::InvalidFloat(2)),
+            ("0x", ErrorKind::InvalidValue(2)),
+            ("0x1.5", ErrorKind::InvalidValue(2)),
+            ("0o8", ErrorKind::InvalidValue(2)),
+            ("0b2", ErrorKind::InvalidValue(2)),
+            ("+", ErrorKind::InvalidValue(2)),
+        ];
+        for (number, error_kind) in invalid_numbers.iter() {
+            let toml_string = format!("first = 1\nnumber = {}", number);
+            let mut parser = super::Parser::new();
+            let error = parser.parse(&toml_string).unwrap_err();
+            assert_eq!(&error.kind(), error_kind, "{}", number);
+        }
+    }
+
+    #[test]
+    fn test_reading_integer_limits() {
+        let toml_string = "max = 9223372036854775807\nmin = -9223372036854775808\nhex = 0x7FFFFFFFFFFFFFFF\noct = 0o777777777777777777777\nbin = 0b111111111111111111111111111111111111111111111111111111111111111\nunderscores = -9_223_372_036_854_775_808";
+        let mut parser = super::Parser::new();
+        let document = parser.parse(toml_string).unwrap();
+        assert_eq!(document.get("max"), Some(&TomlValue::Integer(i64::MAX)));
+        assert_eq!(document.get("min"), Some(&TomlValue::Integer(i64::MIN)));
+        assert_eq!(document.get("hex"), Some(&TomlValue::Integer(i64::MAX)));
+        assert_eq!(document.get("oct"), Some(&TomlValue::Integer(i64::MAX)));
+        assert_eq!(document.get("bin"), Some(&TomlValue::Integer(i64::MAX)));
+        assert_eq!(document.get("underscores"), Some(&TomlValue::Integer(i64::MIN)));
+    }
+
+    #[test]
+    fn test_reading_integer_overflow() {
+        let overflowing_values = ["9223372036854775808", "-9223372036854775809", "99999999999999999999999", "0x8000000000000000", "0o1000000000000000000000", "0b1000000000000000000000000000000000000000000000000000000000000000", "[1, 9_223_372_036_854_775_808]"];
+        for value in overflowing_values.iter() {
+            let toml_string = format!("first = 1\nvalue = {}", value);
+            let mut parser = super::Parser::new();
+            let error = parser.parse(&toml_string).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::IntegerOverflow(2), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_reading_special_float() {
+        let toml_string = "a = inf\nb = +inf\nc = -inf # Comment\nd = [nan, -nan, +nan]";
+        let mut parser = super::Parser::new();
+        let document = parser.parse(toml_string).unwrap();
+        assert_eq!(document.get("a"), Some(&TomlValue::Float(f64::INFINITY)));
+        assert_eq!(document.get("b"), Some(&TomlValue::Float(f64::INFINITY)));
+        assert_eq!(document.get("c"), Some(&TomlValue::Float(f64::NEG_INFINITY)));
+        match document.get("d") {
+            Some(TomlValue::Array(values)) => {
+                // The sign of nan is kept
+                let signs: Vec<Option<bool>> = values
+                    .iter()
+                    .map(|value| match value {
+                        TomlValue::Float(float) if float.is_nan() => Some(float.is_sign_negative()),
+                        _ => None,
+                    })
+                    .collect();
+                assert_eq!(signs, vec![Some(false), Some(true), Some(false)]);
+            }
+            value => panic!("Expected an array, got {:?}", value),
+        }
+    }
+
+    #[test]
+    fn test_special_float_round_trip() {
+        for float in [f64::INFINITY, f64::NEG_INFINITY, f64::NAN, -f64::NAN].iter() {
+            // Write the value the same way a TOML serializer would
+            let written = match float {
+                float if float.is_nan() && float.is_sign_negative() => "-nan".to_string(),
+                float if float.is_nan() => "nan".to_string(),
+                float if float.is_sign_negative() => "-inf".to_string(),
+                _ => "inf".to_string(),
+            };
+            let toml_string = format!("value = {}", written);
+            let mut parser = super::Parser::new();
+            let pair = parser.read_test_pair(&toml_string).unwrap().unwrap();
+            match pair.value() {
+                TomlValue::Float(read) => assert_eq!(read.to_bits(), float.to_bits(), "{}", written),
+                value => panic!("Expected a float, got {:?}", value),
+            }
+        }
+    }
+
+    #[test]
+    fn test_reading_invalid_special_float() {
+        let invalid_values = ["a = infinity", "a = in", "a = NaN", "a = Inf", "a = -na", "a = nan1", "a = inf.0", "a = -infx"];
+        for toml_string in invalid_values.iter() {
+            let mut parser = super::Parser::new();
+            assert!(parser.parse(toml_string).is_err(), "{} should not parse", toml_string);
+        }
+    }
+
     #[test]
     fn test_reading_boolean() {
         let toml_string = "boolean = false\nboolean2 = true";
@@ -494,4 +989,1261 @@ mod test_parser {
             "Expected invalid value on the first line"
         );
     }
+
+    #[test]
+    fn test_reading_comment() {
+        let toml_string = "# A comment\njunk = \"caveman\"\n   # Indented comment\r\naggro = 12";
+        let mut parser = super::Parser::new();
+        let pair = parser.read_test_pair(toml_string).unwrap();
+        assert_eq!(
+            pair,
+            Some(TomlPair::new("junk", TomlValue::String("caveman".into()))),
+            "Failed first test"
+        );
+        let pair = parser.read_test_pair(toml_string).unwrap();
+        assert_eq!(
+            pair,
+            Some(TomlPair::new("aggro", TomlValue::Integer(12))),
+            "Failed second test"
+        );
+        assert_eq!(parser.read_test_pair(toml_string).unwrap(), None);
+    }
+
+    #[test]
+    fn test_reading_comment_after_value() {
+        let toml_string = "string = \"caveman\" # Comment\nboolean = true# Comment\nfloat = 1.5 #\ninteger = 12# Comment\nnegative = -12 # Comment\nzero = 0# Comment\nhex = 0xff# Comment\noctal = 0o17 # Comment\nbinary = 0b11# Comment";
+        let mut parser = super::Parser::new();
+        let expected = vec![
+            TomlPair::new("string", TomlValue::String("caveman".into())),
+            TomlPair::new("boolean", TomlValue::Bool(true)),
+            TomlPair::new("float", TomlValue::Float(1.5)),
+            TomlPair::new("integer", TomlValue::Integer(12)),
+            TomlPair::new("negative", TomlValue::Integer(-12)),
+            TomlPair::new("zero", TomlValue::Integer(0)),
+            TomlPair::new("hex", TomlValue::Integer(0xff)),
+            TomlPair::new("octal", TomlValue::Integer(0o17)),
+            TomlPair::new("binary", TomlValue::Integer(0b11)),
+        ];
+        for expected_pair in expected {
+            let pair = parser.read_test_pair(toml_string).unwrap();
+            assert_eq!(pair, Some(expected_pair));
+        }
+        assert_eq!(parser.read_test_pair(toml_string).unwrap(), None);
+    }
+
+    #[test]
+    fn test_reading_comment_at_end_of_file() {
+        let toml_string = "junk = -0 # Comment with no new line";
+        let mut parser = super::Parser::new();
+        let pair = parser.read_test_pair(toml_string).unwrap();
+        assert_eq!(pair, Some(TomlPair::new("junk", TomlValue::Integer(0))));
+        assert_eq!(parser.read_test_pair(toml_string).unwrap(), None);
+    }
+
+    #[test]
+    fn test_reading_comment_with_control_character() {
+        let toml_string = "# Comment\njunk = 1 # Comment with a \u{7} bell";
+        let mut parser = super::Parser::new();
+        parser.read_test_pair(toml_string).unwrap();
+        let error = parser.read_test_pair(toml_string).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidComment(2));
+
+        let toml_string2 = "# Tabs\tare allowed but \u{0} is not";
+        let mut parser2 = super::Parser::new();
+        let error2 = parser2.read_test_pair(toml_string2).unwrap_err();
+        assert_eq!(error2.kind(), ErrorKind::InvalidComment(1));
+    }
+
+    #[test]
+    fn test_parse_with_comments() {
+        let toml_string = "# This is a TOML document\n\ntitle = \"TOML Example\" # The title\n# port = 80\nport = 8080\n";
+        let mut parser = super::Parser::new();
+        let values = parser.parse(toml_string).unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values.get("title"), Some(&TomlValue::String("TOML Example".into())));
+        assert_eq!(values.get("port"), Some(&TomlValue::Integer(8080)));
+    }
+
+    #[test]
+    fn test_reading_table_header() {
+        let toml_string = "[server]\nport = 80\n[ server.tls ] # Comment\ncert = \"cert.pem\"";
+        let mut parser = super::Parser::new();
+        let statement = parser.read_test_statement(toml_string).unwrap();
+        assert_eq!(statement, Some(TomlStatement::Table(vec!["server".into()])));
+        let pair = parser.read_test_pair(toml_string).unwrap();
+        assert_eq!(pair, Some(TomlPair::new("port", TomlValue::Integer(80))));
+        let statement = parser.read_test_statement(toml_string).unwrap();
+        assert_eq!(statement, Some(TomlStatement::Table(vec!["server".into(), "tls".into()])));
+        let pair = parser.read_test_pair(toml_string).unwrap();
+        assert_eq!(
+            pair,
+            Some(TomlPair::new("cert", TomlValue::String("cert.pem".into())))
+        );
+        assert_eq!(parser.read_test_statement(toml_string).unwrap(), None);
+    }
+
+    #[test]
+    fn test_reading_table_header2() {
+        let toml_string = "[ a . b\t.c ]";
+        let mut parser = super::Parser::new();
+        let statement = parser.read_test_statement(toml_string).unwrap();
+        assert_eq!(statement, Some(TomlStatement::Table(vec!["a".into(), "b".into(), "c".into()])));
+    }
+
+    #[test]
+    fn test_reading_invalid_table_header() {
+        let invalid_headers = ["[server", "[]", "[server.]", "[.server]", "[server tls]", "[server\n]", "[server] junk"];
+        for toml_string in invalid_headers.iter() {
+            let mut parser = super::Parser::new();
+            let result = parser.parse(toml_string);
+            assert!(result.is_err(), "{} should not parse", toml_string);
+        }
+    }
+
+    #[test]
+    fn test_parse_tables() {
+        let toml_string = "title = \"Example\"\n\n[server]\nport = 80\n\n[server.tls]\ncert = \"cert.pem\"\n\n[client]\n";
+        let mut parser = super::Parser::new();
+        let document = parser.parse(toml_string).unwrap();
+        assert_eq!(document.get("title"), Some(&TomlValue::String("Example".into())));
+        let server = match document.get("server") {
+            Some(TomlValue::Table(server)) => server,
+            value => panic!("Expected server to be a table but it was {:?}", value),
+        };
+        assert_eq!(server.get("port"), Some(&TomlValue::Integer(80)));
+        let tls = match server.get("tls") {
+            Some(TomlValue::Table(tls)) => tls,
+            value => panic!("Expected tls to be a table but it was {:?}", value),
+        };
+        assert_eq!(tls.get("cert"), Some(&TomlValue::String("cert.pem".into())));
+        assert_eq!(document.get("client"), Some(&TomlValue::Table(TomlTable::new())));
+    }
+
+    #[test]
+    fn test_parse_implicit_table() {
+        // The parent table can be defined after the child table
+        let toml_string = "[x.y.z]\nvalue = 1\n[x]\nvalue = 2";
+        let mut parser = super::Parser::new();
+        let document = parser.parse(toml_string).unwrap();
+        let x = match document.get("x") {
+            Some(TomlValue::Table(x)) => x,
+            value => panic!("Expected x to be a table but it was {:?}", value),
+        };
+        assert_eq!(x.get("value"), Some(&TomlValue::Integer(2)));
+        assert!(matches!(x.get("y"), Some(TomlValue::Table(_))));
+    }
+
+    #[test]
+    fn test_parse_table_redefined() {
+        let toml_string = "[fruit]\napple = \"red\"\n\n[fruit]\norange = \"orange\"";
+        let mut parser = super::Parser::new();
+        let error = parser.parse(toml_string).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TableRedefined(4));
+
+        let toml_string2 = "[x.y]\n[x]\n[x.y]";
+        let mut parser2 = super::Parser::new();
+        let error2 = parser2.parse(toml_string2).unwrap_err();
+        assert_eq!(error2.kind(), ErrorKind::TableRedefined(3));
+
+        let toml_string3 = "fruit = 1\n[fruit.apple]";
+        let mut parser3 = super::Parser::new();
+        let error3 = parser3.parse(toml_string3).unwrap_err();
+        assert_eq!(
+            error3.kind(),
+            ErrorKind::DuplicateKey {
+                key: "fruit".to_string(),
+                line_number: 2,
+                first_line_number: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_reading_array_of_tables_header() {
+        let toml_string = "[[plugin]]\nname = \"first\"\n[[ fruit.variety ]]";
+        let mut parser = super::Parser::new();
+        let statement = parser.read_test_statement(toml_string).unwrap();
+        assert_eq!(statement, Some(TomlStatement::ArrayOfTables(vec!["plugin".into()])));
+        parser.read_test_pair(toml_string).unwrap();
+        let statement = parser.read_test_statement(toml_string).unwrap();
+        assert_eq!(
+            statement,
+            Some(TomlStatement::ArrayOfTables(vec!["fruit".into(), "variety".into()]))
+        );
+    }
+
+    #[test]
+    fn test_reading_invalid_array_of_tables_header() {
+        let invalid_headers = ["[[plugin]", "[[plugin] ]", "[ [plugin]]", "[[]]", "[[plugin]]]"];
+        for toml_string in invalid_headers.iter() {
+            let mut parser = super::Parser::new();
+            let result = parser.parse(toml_string);
+            assert!(result.is_err(), "{} should not parse", toml_string);
+        }
+    }
+
+    #[test]
+    fn test_parse_array_of_tables() {
+        let toml_string = "[[plugin]]\nname = \"first\"\n\n[[plugin]]\n\n[[plugin]]\nname = \"third\"\n";
+        let mut parser = super::Parser::new();
+        let document = parser.parse(toml_string).unwrap();
+        let mut first = TomlTable::new();
+        first.insert("name".into(), TomlValue::String("first".into()));
+        let mut third = TomlTable::new();
+        third.insert("name".into(), TomlValue::String("third".into()));
+        assert_eq!(
+            document.get("plugin"),
+            Some(&TomlValue::Array(vec![
+                TomlValue::Table(first),
+                TomlValue::Table(TomlTable::new()),
+                TomlValue::Table(third),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_parse_nested_array_of_tables() {
+        let toml_string = "[[fruit]]\nname = \"apple\"\n\n[fruit.physical]\ncolor = \"red\"\n\n[[fruit.variety]]\nname = \"red delicious\"\n\n[[fruit.variety]]\nname = \"granny smith\"\n\n[[fruit]]\nname = \"banana\"\n\n[fruit.physical]\ncolor = \"yellow\"\n\n[[fruit.variety]]\nname = \"plantain\"\n";
+        let mut parser = super::Parser::new();
+        let document = parser.parse(toml_string).unwrap();
+        let fruit = match document.get("fruit") {
+            Some(TomlValue::Array(fruit)) => fruit,
+            value => panic!("Expected fruit to be an array but it was {:?}", value),
+        };
+        assert_eq!(fruit.len(), 2);
+        let banana = match &fruit[1] {
+            TomlValue::Table(banana) => banana,
+            value => panic!("Expected a table but found {:?}", value),
+        };
+        assert_eq!(banana.get("name"), Some(&TomlValue::String("banana".into())));
+        let mut plantain = TomlTable::new();
+        plantain.insert("name".into(), TomlValue::String("plantain".into()));
+        assert_eq!(
+            banana.get("variety"),
+            Some(&TomlValue::Array(vec![TomlValue::Table(plantain)]))
+        );
+        match &fruit[0] {
+            TomlValue::Table(apple) => match apple.get("variety") {
+                Some(TomlValue::Array(variety)) => assert_eq!(variety.len(), 2),
+                value => panic!("Expected variety to be an array but it was {:?}", value),
+            },
+            value => panic!("Expected a table but found {:?}", value),
+        }
+    }
+
+    #[test]
+    fn test_parse_array_of_tables_collision() {
+        // A normal table can't be turned into an array of tables
+        let toml_string = "[fruit]\n[[fruit]]";
+        let mut parser = super::Parser::new();
+        let error = parser.parse(toml_string).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidArrayOfTables(2));
+
+        // An array of tables can't be defined as a normal table
+        let toml_string2 = "[[fruit]]\n[fruit]";
+        let mut parser2 = super::Parser::new();
+        let error2 = parser2.parse(toml_string2).unwrap_err();
+        assert_eq!(error2.kind(), ErrorKind::TableRedefined(2));
+
+        // A sub table can only be defined once per table in the array
+        let toml_string3 = "[[fruit]]\n[fruit.physical]\n[fruit.physical]";
+        let mut parser3 = super::Parser::new();
+        let error3 = parser3.parse(toml_string3).unwrap_err();
+        assert_eq!(error3.kind(), ErrorKind::TableRedefined(3));
+    }
+
+    #[test]
+    fn test_reading_array() {
+        let toml_string = "ports = [ 80, 443,8080 ]";
+        let mut parser = super::Parser::new();
+        let pair = parser.read_test_pair(toml_string).unwrap();
+        assert_eq!(
+            pair,
+            Some(TomlPair::new(
+                "ports",
+                TomlValue::Array(vec![
+                    TomlValue::Integer(80),
+                    TomlValue::Integer(443),
+                    TomlValue::Integer(8080)
+                ])
+            ))
+        );
+        assert_eq!(parser.read_test_pair(toml_string).unwrap(), None);
+    }
+
+    #[test]
+    fn test_reading_array2() {
+        let toml_string = "empty = []\nmixed = [\"a\", 1.5, true, -2, 0x1f, 0, false]";
+        let mut parser = super::Parser::new();
+        let pair = parser.read_test_pair(toml_string).unwrap();
+        assert_eq!(pair, Some(TomlPair::new("empty", TomlValue::Array(vec![]))));
+        let pair = parser.read_test_pair(toml_string).unwrap();
+        assert_eq!(
+            pair,
+            Some(TomlPair::new(
+                "mixed",
+                TomlValue::Array(vec![
+                    TomlValue::String("a".into()),
+                    TomlValue::Float(1.5),
+                    TomlValue::Bool(true),
+                    TomlValue::Integer(-2),
+                    TomlValue::Integer(0x1f),
+                    TomlValue::Integer(0),
+                    TomlValue::Bool(false),
+                ])
+            ))
+        );
+    }
+
+    #[test]
+    fn test_reading_multiline_array() {
+        let toml_string = "numbers = [ # Comment after the start\r\n  1,\n  2, # Comment after a value\n\n  3 # Comment before a comma\n  ,\n] # Comment after the array\nafter = 4";
+        let mut parser = super::Parser::new();
+        let pair = parser.read_test_pair(toml_string).unwrap();
+        assert_eq!(
+            pair,
+            Some(TomlPair::new(
+                "numbers",
+                TomlValue::Array(vec![
+                    TomlValue::Integer(1),
+                    TomlValue::Integer(2),
+                    TomlValue::Integer(3)
+                ])
+            ))
+        );
+        let pair = parser.read_test_pair(toml_string).unwrap();
+        assert_eq!(pair, Some(TomlPair::new("after", TomlValue::Integer(4))));
+    }
+
+    #[test]
+    fn test_reading_nested_array() {
+        let toml_string = "nested = [ [ 1, 2 ], [\"a\", [ ] ], ]";
+        let mut parser = super::Parser::new();
+        let pair = parser.read_test_pair(toml_string).unwrap();
+        assert_eq!(
+            pair,
+            Some(TomlPair::new(
+                "nested",
+                TomlValue::Array(vec![
+                    TomlValue::Array(vec![TomlValue::Integer(1), TomlValue::Integer(2)]),
+                    TomlValue::Array(vec![TomlValue::String("a".into()), TomlValue::Array(vec![])]),
+                ])
+            ))
+        );
+    }
+
+    #[test]
+    fn test_reading_invalid_array() {
+        let invalid_arrays = ["a = [,]", "a = [1,,2]", "a = [1 2]", "a = [1] 2", "a = [1]]", "a = [tru]"];
+        for toml_string in invalid_arrays.iter() {
+            let mut parser = super::Parser::new();
+            let result = parser.parse(toml_string);
+            assert!(result.is_err(), "{} should not parse", toml_string);
+        }
+    }
+
+    #[test]
+    fn test_reading_unterminated_array() {
+        let toml_string = "before = 1\narray = [\n  1,\n  [2, 3],\n";
+        let mut parser = super::Parser::new();
+        let error = parser.parse(toml_string).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnterminatedArray(2));
+
+        let toml_string2 = "array = [1, [2, 3";
+        let mut parser2 = super::Parser::new();
+        let error2 = parser2.parse(toml_string2).unwrap_err();
+        assert_eq!(error2.kind(), ErrorKind::UnterminatedArray(1));
+    }
+
+    #[test]
+    fn test_parse_static_array_collision() {
+        // A static array can't be extended with an array of tables
+        let toml_string = "fruit = []\n[[fruit]]";
+        let mut parser = super::Parser::new();
+        let error = parser.parse(toml_string).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidArrayOfTables(2));
+
+        let toml_string2 = "fruit = [1]\n[fruit.apple]";
+        let mut parser2 = super::Parser::new();
+        let error2 = parser2.parse(toml_string2).unwrap_err();
+        assert_eq!(
+            error2.kind(),
+            ErrorKind::DuplicateKey {
+                key: "fruit".to_string(),
+                line_number: 2,
+                first_line_number: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_reading_inline_table() {
+        let toml_string = "point = { x = 1, y = 2 }\nempty = {}";
+        let mut parser = super::Parser::new();
+        let mut point = TomlTable::new();
+        point.insert("x".into(), TomlValue::Integer(1));
+        point.insert("y".into(), TomlValue::Integer(2));
+        let pair = parser.read_test_pair(toml_string).unwrap();
+        assert_eq!(pair, Some(TomlPair::new("point", TomlValue::Table(point))));
+        let pair = parser.read_test_pair(toml_string).unwrap();
+        assert_eq!(
+            pair,
+            Some(TomlPair::new("empty", TomlValue::Table(TomlTable::new())))
+        );
+        assert_eq!(parser.read_test_pair(toml_string).unwrap(), None);
+    }
+
+    #[test]
+    fn test_reading_nested_inline_table() {
+        let toml_string = "outer = {inner={ value = \"a\" },list = [ 1, { flag = true } ]} # Comment";
+        let mut parser = super::Parser::new();
+        let mut inner = TomlTable::new();
+        inner.insert("value".into(), TomlValue::String("a".into()));
+        let mut flag = TomlTable::new();
+        flag.insert("flag".into(), TomlValue::Bool(true));
+        let mut outer = TomlTable::new();
+        outer.insert("inner".into(), TomlValue::Table(inner));
+        outer.insert(
+            "list".into(),
+            TomlValue::Array(vec![TomlValue::Integer(1), TomlValue::Table(flag)]),
+        );
+        let pair = parser.read_test_pair(toml_string).unwrap();
+        assert_eq!(pair, Some(TomlPair::new("outer", TomlValue::Table(outer))));
+        assert_eq!(parser.read_test_pair(toml_string).unwrap(), None);
+    }
+
+    #[test]
+    fn test_reading_inline_table_with_multiline_array() {
+        // New lines are allowed inside a value of an inline table
+        let toml_string = "table = { list = [\n  1,\n  2,\n] }\nafter = 3";
+        let mut parser = super::Parser::new();
+        let document = parser.parse(toml_string).unwrap();
+        let mut table = TomlTable::new();
+        table.insert(
+            "list".into(),
+            TomlValue::Array(vec![TomlValue::Integer(1), TomlValue::Integer(2)]),
+        );
+        assert_eq!(document.get("table"), Some(&TomlValue::Table(table)));
+        assert_eq!(document.get("after"), Some(&TomlValue::Integer(3)));
+    }
+
+    #[test]
+    fn test_reading_invalid_inline_table() {
+        let toml_string = "point = { x = 1,\n y = 2 }";
+        let mut parser = super::Parser::new();
+        let error = parser.parse(toml_string).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInlineTable(1));
+
+        let toml_string2 = "first = 1\npoint = { x = 1, }";
+        let mut parser2 = super::Parser::new();
+        let error2 = parser2.parse(toml_string2).unwrap_err();
+        assert_eq!(error2.kind(), ErrorKind::InvalidInlineTable(2));
+
+        let toml_string3 = "point = { x = 1";
+        let mut parser3 = super::Parser::new();
+        let error3 = parser3.parse(toml_string3).unwrap_err();
+        assert_eq!(error3.kind(), ErrorKind::InvalidInlineTable(1));
+
+        let invalid_tables = ["a = { , }", "a = { x = 1 y = 2 }", "a = { x }", "a = { x = 1 } }", "a = { x = 1 # Comment\n}"];
+        for toml_string in invalid_tables.iter() {
+            let mut parser = super::Parser::new();
+            let result = parser.parse(toml_string);
+            assert!(result.is_err(), "{} should not parse", toml_string);
+        }
+    }
+
+    #[test]
+    fn test_parse_inline_table_matches_table() {
+        let inline = "[server]\ntls = { cert = \"cert.pem\", port = 443 }";
+        let standard = "[server.tls]\ncert = \"cert.pem\"\nport = 443";
+        let mut parser = super::Parser::new();
+        let mut parser2 = super::Parser::new();
+        assert_eq!(parser.parse(inline).unwrap(), parser2.parse(standard).unwrap());
+    }
+
+    #[test]
+    fn test_parse_inline_table_is_sealed() {
+        let toml_string = "[product]\ntype = { name = \"Nail\" }\n\n[product.type]\nedible = false";
+        let mut parser = super::Parser::new();
+        let error = parser.parse(toml_string).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TableRedefined(4));
+
+        let toml_string2 = "a = { b = {} }\n[a.b.c]";
+        let mut parser2 = super::Parser::new();
+        let error2 = parser2.parse(toml_string2).unwrap_err();
+        assert_eq!(error2.kind(), ErrorKind::TableRedefined(2));
+    }
+
+    #[test]
+    fn test_reading_dotted_name() {
+        let toml_string = "physical.color = \"orange\"";
+        let mut parser = super::Parser::new();
+        let pair = parser.read_test_pair(toml_string).unwrap();
+        assert_eq!(
+            pair,
+            Some(TomlPair::new_dotted(
+                vec!["physical".into(), "color".into()],
+                TomlValue::String("orange".into())
+            ))
+        );
+        let mut parser2 = super::Parser::new();
+        let pair2 = parser2.read_test_pair("a . b\t.\tc=1").unwrap();
+        assert_eq!(
+            pair2,
+            Some(TomlPair::new_dotted(
+                vec!["a".into(), "b".into(), "c".into()],
+                TomlValue::Integer(1)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_reading_invalid_dotted_name() {
+        let invalid_names = ["a. = 1", "a..b = 1", ".a = 1", "a = { .b = 1 }", "= 1", "a.\nb = 1", "a. # Comment", "a.b"];
+        for toml_string in invalid_names.iter() {
+            let mut parser = super::Parser::new();
+            let result = parser.parse(toml_string);
+            assert!(result.is_err(), "{} should not parse", toml_string);
+        }
+    }
+
+    #[test]
+    fn test_parse_dotted_name() {
+        let dotted = "name = \"Orange\"\nphysical.color = \"orange\"\nphysical.shape = \"round\"";
+        let standard = "name = \"Orange\"\n[physical]\ncolor = \"orange\"\nshape = \"round\"";
+        let mut parser = super::Parser::new();
+        let mut parser2 = super::Parser::new();
+        assert_eq!(parser.parse(dotted).unwrap(), parser2.parse(standard).unwrap());
+
+        let toml_string = "[fruit]\napple.color = \"red\"\napple.taste.sweet = true\n\n[fruit.apple.texture]\nsmooth = true";
+        let mut parser3 = super::Parser::new();
+        let document = parser3.parse(toml_string).unwrap();
+        let mut texture = TomlTable::new();
+        texture.insert("smooth".into(), TomlValue::Bool(true));
+        let mut taste = TomlTable::new();
+        taste.insert("sweet".into(), TomlValue::Bool(true));
+        let mut apple = TomlTable::new();
+        apple.insert("color".into(), TomlValue::String("red".into()));
+        apple.insert("taste".into(), TomlValue::Table(taste));
+        apple.insert("texture".into(), TomlValue::Table(texture));
+        let mut fruit = TomlTable::new();
+        fruit.insert("apple".into(), TomlValue::Table(apple));
+        assert_eq!(document.get("fruit"), Some(&TomlValue::Table(fruit)));
+    }
+
+    #[test]
+    fn test_parse_dotted_name_in_inline_table() {
+        let inline = "point = { x.value = 1, x.unit = \"mm\" }";
+        let standard = "[point.x]\nvalue = 1\nunit = \"mm\"";
+        let mut parser = super::Parser::new();
+        let mut parser2 = super::Parser::new();
+        assert_eq!(parser.parse(inline).unwrap(), parser2.parse(standard).unwrap());
+
+        let mut parser3 = super::Parser::new();
+        let error = parser3.parse("point = { x = 1, x.y = 2 }").unwrap_err();
+        assert_eq!(
+            error.kind(),
+            ErrorKind::DuplicateKey {
+                key: "point.x".to_string(),
+                line_number: 1,
+                first_line_number: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_dotted_name_collision() {
+        let toml_string = "[fruit]\napple.color = \"red\"\n\n[fruit.apple]\ncolor = \"green\"";
+        let mut parser = super::Parser::new();
+        let error = parser.parse(toml_string).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TableRedefined(4));
+
+        let toml_string2 = "[fruit.apple]\ncolor = \"red\"\n\n[fruit]\napple.taste = \"sweet\"";
+        let mut parser2 = super::Parser::new();
+        let error2 = parser2.parse(toml_string2).unwrap_err();
+        assert_eq!(error2.kind(), ErrorKind::TableRedefined(5));
+
+        let toml_string3 = "fruit = 1\nfruit.apple = 2";
+        let mut parser3 = super::Parser::new();
+        let error3 = parser3.parse(toml_string3).unwrap_err();
+        assert_eq!(
+            error3.kind(),
+            ErrorKind::DuplicateKey {
+                key: "fruit".to_string(),
+                line_number: 2,
+                first_line_number: 1
+            }
+        );
+
+        let toml_string4 = "fruit.apple = 1\n[[fruit]]";
+        let mut parser4 = super::Parser::new();
+        let error4 = parser4.parse(toml_string4).unwrap_err();
+        assert_eq!(error4.kind(), ErrorKind::InvalidArrayOfTables(2));
+    }
+
+    #[test]
+    fn test_parse_duplicate_key() {
+        let duplicates = [
+            ("port = 80\nport = 8080", "port", 2, 1),
+            ("a.b = 1\n\na.b = 2", "a.b", 3, 1),
+            ("[server]\nport = 80\n# Comment\nport = 8080", "server.port", 4, 2),
+            ("[table]\na = 1\n[other]\n[table.sub]\nb = 2\npoint = { x = 1, x = 2 }", "table.sub.point.x", 6, 6),
+            ("[srv]\nt = { b = 1, b = 2 }", "srv.t.b", 2, 2),
+            ("[srv]\nt = { a = { b = 1, b = 2 } }", "srv.t.a.b", 2, 2),
+            ("t = [{ a.b = 1, a.b = 2 }]", "t.a.b", 1, 1),
+            ("[fruit]\napple.color = \"red\"\napple.color = \"green\"", "fruit.apple.color", 3, 2),
+            ("[fruit]\napple.color = \"red\"\napple = 1", "fruit.apple", 3, 2),
+            ("[fruit]\napple = 1\napple.color = \"red\"", "fruit.apple", 3, 2),
+            ("[a]\nb = 1\n\n[a.b]", "a.b", 4, 2),
+            ("[a]\nb = 1\n\n[a.b.c]", "a.b", 4, 2),
+            ("[a.b]\nc = 1\n[a]\nb = 2", "a.b", 4, 1),
+            ("fruit = [1]\n[fruit.apple]", "fruit", 2, 1),
+            ("[[fruit]]\nname = 1\nname = 2", "fruit.name", 3, 2),
+            ("\"a.b\" = 1\n\"a.b\" = 2", "\"a.b\"", 2, 1),
+            ("[\"my table\"]\n\"\" = 1\n\"\" = 2", "\"my table\".\"\"", 3, 2),
+            // Values that span several lines are given the line of their name
+            ("a = [\n1,\n2]\na = 3", "a", 4, 1),
+            ("a = \"\"\"\n\n\n\"\"\"\na = 1", "a", 5, 1),
+            ("t = [{ b = 1 },\n{ c = 2 }]\nt = 1", "t", 3, 1),
+            ("a = 1\na = [\n1]", "a", 2, 1),
+        ];
+        for (toml_string, key, line_number, first_line_number) in duplicates.iter() {
+            let mut parser = super::Parser::new();
+            let error = parser.parse(toml_string).unwrap_err();
+            assert_eq!(
+                error.kind(),
+                ErrorKind::DuplicateKey {
+                    key: key.to_string(),
+                    line_number: *line_number,
+                    first_line_number: *first_line_number
+                },
+                "{}",
+                toml_string
+            );
+        }
+
+        // Each table in an array of tables has its own keys
+        let toml_string = "[[fruit]]\nname = 1\n[[fruit]]\nname = 2";
+        let mut parser = super::Parser::new();
+        assert!(parser.parse(toml_string).is_ok());
+    }
+
+    #[test]
+    fn test_duplicate_key_display() {
+        let toml_string = "port = 80\nport = 8080";
+        let mut parser = super::Parser::new();
+        let error = parser.parse(toml_string).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Error in TOML Parser at port, error was The name port on line 2 was already given a value on line 1"
+        );
+    }
+
+    #[test]
+    fn test_reading_quoted_name() {
+        let toml_string = "\"127.0.0.1\" = \"localhost\"\n'quoted \"value\"' = 1\n\"\" = 2\n\"tab\\tname\" = 3\n\"\\u00e9t\\U0001F600\"=4";
+        let mut parser = super::Parser::new();
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert_eq!(pair.name(), ["127.0.0.1"]);
+        assert_eq!(pair.value(), &TomlValue::String("localhost".into()));
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert_eq!(pair.name(), ["quoted \"value\""]);
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert_eq!(pair.name(), [""]);
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert_eq!(pair.name(), ["tab\tname"]);
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert_eq!(pair.name(), ["\u{e9}t\u{1F600}"]);
+        assert_eq!(parser.read_test_pair(toml_string).unwrap(), None);
+    }
+
+    #[test]
+    fn test_reading_dotted_quoted_name() {
+        let toml_string = "site.\"google.com\" = true\n 'a' . \"b\\\"c\" .d = 1";
+        let mut parser = super::Parser::new();
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert_eq!(pair.name(), ["site", "google.com"]);
+        let pair = parser.read_test_pair(toml_string).unwrap().unwrap();
+        assert_eq!(pair.name(), ["a", "b\"c", "d"]);
+
+        let toml_string2 = "[dog.\"tater.man\"]\ntype.name = \"pug\"\n[ 'a' . \"\" ]";
+        let mut parser2 = super::Parser::new();
+        let statement = parser2.read_test_statement(toml_string2).unwrap();
+        assert_eq!(
+            statement,
+            Some(TomlStatement::Table(vec!["dog".into(), "tater.man".into()]))
+        );
+        parser2.read_test_statement(toml_string2).unwrap();
+        let statement = parser2.read_test_statement(toml_string2).unwrap();
+        assert_eq!(statement, Some(TomlStatement::Table(vec!["a".into(), "".into()])));
+    }
+
+    #[test]
+    fn test_parse_quoted_name() {
+        let toml_string = "\"a.b\" = 1\na.b = 2\npoint = { \"x\" = 1, 'y' = 2 }";
+        let mut parser = super::Parser::new();
+        let document = parser.parse(toml_string).unwrap();
+        assert_eq!(document.get("a.b"), Some(&TomlValue::Integer(1)));
+        let mut a = TomlTable::new();
+        a.insert("b".into(), TomlValue::Integer(2));
+        assert_eq!(document.get("a"), Some(&TomlValue::Table(a)));
+        let mut point = TomlTable::new();
+        point.insert("x".into(), TomlValue::Integer(1));
+        point.insert("y".into(), TomlValue::Integer(2));
+        assert_eq!(document.get("point"), Some(&TomlValue::Table(point)));
+
+        // Quoted and bare names that decode to the same name are the same key
+        let toml_string2 = "name = 1\n\"name\" = 2";
+        let mut parser2 = super::Parser::new();
+        let error = parser2.parse(toml_string2).unwrap_err();
+        assert_eq!(
+            error.kind(),
+            ErrorKind::DuplicateKey {
+                key: "name".to_string(),
+                line_number: 2,
+                first_line_number: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_reading_invalid_quoted_name() {
+        let invalid_names = ["\"name = 1", "'name = 1", "\"na\nme\" = 1", "\"a\"b = 1", "'a' 'b' = 1", "[\"a\"b]", "\"\\\" = 1"];
+        for toml_string in invalid_names.iter() {
+            let mut parser = super::Parser::new();
+            let result = parser.parse(toml_string);
+            assert!(result.is_err(), "{} should not parse", toml_string);
+        }
+
+        let invalid_escapes = ["\"\\x41\" = 1", "\"\\u12\" = 1", "\"\\uD800\" = 1", "\"\\U00110000\" = 1", "a = 1\n\"\\ \" = 1"];
+        for toml_string in invalid_escapes.iter() {
+            let mut parser = super::Parser::new();
+            let error = parser.parse(toml_string).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidEscape(toml_string.lines().count()), "{}", toml_string);
+        }
+    }
+
+    #[test]
+    fn test_reading_date_time() {
+        let toml_string = "odt1 = 1979-05-27T07:32:00Z\nodt2 = 1979-05-27T00:32:00-07:00\nodt3 = 1979-05-27 00:32:00.999999+05:30\nldt = 1979-05-27t07:32:00.123456789123\nld = 1979-05-27 # Comment\nlt = 00:32:00.5\nlist = [1979-05-27, 07:32:00]";
+        let date = LocalDate {
+            year: 1979,
+            month: 5,
+            day: 27,
+        };
+        let mut parser = super::Parser::new();
+        let document = parser.parse(toml_string).unwrap();
+        assert_eq!(
+            document.get("odt1"),
+            Some(&TomlValue::OffsetDateTime(OffsetDateTime {
+                date,
+                time: LocalTime {
+                    hour: 7,
+                    minute: 32,
+                    second: 0,
+                    nanosecond: 0
+                },
+                offset: 0
+            }))
+        );
+        match document.get("odt2") {
+            Some(TomlValue::OffsetDateTime(date_time)) => {
+                assert_eq!(date_time.offset, -7 * 60);
+                assert_eq!(date_time.to_string(), "1979-05-27T00:32:00-07:00");
+            }
+            value => panic!("Expected an offset date time, got {:?}", value),
+        }
+        match document.get("odt3") {
+            Some(TomlValue::OffsetDateTime(date_time)) => {
+                assert_eq!(date_time.time.nanosecond, 999_999_000);
+                assert_eq!(date_time.to_string(), "1979-05-27T00:32:00.999999+05:30");
+            }
+            value => panic!("Expected an offset date time, got {:?}", value),
+        }
+        // Precision beyond nanoseconds is truncated
+        assert_eq!(
+            document.get("ldt"),
+            Some(&TomlValue::LocalDateTime(LocalDateTime {
+                date,
+                time: LocalTime {
+                    hour: 7,
+                    minute: 32,
+                    second: 0,
+                    nanosecond: 123_456_789
+                }
+            }))
+        );
+        assert_eq!(document.get("ld"), Some(&TomlValue::LocalDate(date)));
+        assert_eq!(
+            document.get("lt"),
+            Some(&TomlValue::LocalTime(LocalTime {
+                hour: 0,
+                minute: 32,
+                second: 0,
+                nanosecond: 500_000_000
+            }))
+        );
+        match document.get("list") {
+            Some(TomlValue::Array(values)) => {
+                assert_eq!(values[0], TomlValue::LocalDate(date));
+                assert!(matches!(values[1], TomlValue::LocalTime(_)));
+            }
+            value => panic!("Expected an array, got {:?}", value),
+        }
+    }
+
+    #[test]
+    fn test_reading_date_time_ranges() {
+        let valid_values = ["2000-02-29", "2024-02-29", "1999-12-31T23:59:60Z", "0001-01-01T00:00:00+23:59"];
+        for value in valid_values.iter() {
+            let toml_string = format!("a = {}", value);
+            let mut parser = super::Parser::new();
+            assert!(parser.parse(&toml_string).is_ok(), "{} should parse", value);
+        }
+
+        let invalid_values = ["1900-02-29", "2023-02-29", "1979-13-01", "1979-00-01", "1979-04-31", "1979-05-00", "24:00:00", "07:60:00", "07:32:60", "07:32:61", "07:32", "07:32:00.", "1979-05-27T07:32:00+24:00", "1979-05-27T07:32:00+05", "1979-5-27", "1979-05-27X07:32:00", "1979-05-27T07:32:00Zulu"];
+        for value in invalid_values.iter() {
+            let toml_string = format!("b = 1\na = {}", value);
+            let mut parser = super::Parser::new();
+            let error = parser.parse(&toml_string).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidDateTime(2), "{}", value);
+        }
+        let mut parser = super::Parser::new();
+        assert!(parser.parse("a = +1979-05-27").is_err());
+    }
+
+    #[test]
+    fn test_reading_invalid_bare_name() {
+        let invalid_names = ["a$b = 1", "é = 1", "a.b!c = 1", "[ta+ble]", "[a.🙂]", "x = { a%b = 1 }", "x = { ö = 1 }"];
+        for toml_string in invalid_names.iter() {
+            let mut parser = super::Parser::new();
+            let error = parser.parse(toml_string).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidBareKey(1), "{}", toml_string);
+        }
+
+        let mut parser = super::Parser::new();
+        let table = parser.parse("\"é\" = 1\nA-z_09 = 2").unwrap();
+        assert_eq!(table.get("é"), Some(&TomlValue::Integer(1)));
+        assert_eq!(table.get("A-z_09"), Some(&TomlValue::Integer(2)));
+    }
+
+    #[test]
+    fn test_reading_control_characters() {
+        let invalid_strings = ["a = \"bell\u{7}\"", "a = 'null\u{0}'", "a = \"\"\"delete\u{7F}\"\"\"", "a = '''escape\u{1B}'''", "\"bell\u{7}\" = 1", "'vertical\u{B}tab' = 1"];
+        for toml_string in invalid_strings.iter() {
+            let mut parser = super::Parser::new();
+            let error = parser.parse(toml_string).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidControlCharacter(1), "{}", toml_string);
+        }
+
+        let mut parser = super::Parser::new();
+        let error = parser.parse("\"new\nline\" = 1").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidName(1));
+    }
+
+    #[test]
+    fn test_parse_byte_order_mark() {
+        let mut parser = super::Parser::new();
+        let table = parser.parse("\u{FEFF}a = 1\n").unwrap();
+        assert_eq!(table.get("a"), Some(&TomlValue::Integer(1)));
+
+        let mut parser = super::Parser::new();
+        assert!(parser.parse("\u{FEFF}").unwrap().is_empty());
+
+        // Only a byte order mark at the start of the document is skipped
+        let mut parser = super::Parser::new();
+        let error = parser.parse("a = 1\n\u{FEFF}b = 2").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidBareKey(2));
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        let mut parser = super::Parser::new();
+        let table = parser.parse_bytes(b"a = \"b\"\n").unwrap();
+        assert_eq!(table.get("a"), Some(&TomlValue::String(Cow::Borrowed("b"))));
+
+        let mut parser = super::Parser::new();
+        let error = parser.parse_bytes(b"a = 1\nb = \"\xFF\"").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidUtf8(2));
+    }
+
+    #[test]
+    fn test_parse_adversarial_input() {
+        // None of these are valid TOML, each one must be reported as an error rather than a panic
+        let invalid_documents = [
+            "\"", "'", "=", "[", "[[", "]", "{", "a", "a =", "a = ", "a = \"", "a = '", "a = \"\"\"", "a = '''",
+            "a = \"\\", "a = \"\\u", "a = \"\\U0000", "\"a", "'a", "\"\\", "a.", "a.\"", "[a.", "[\"a", "[[a]", "[[a] ]",
+            "a = [", "a = [1,", "a = [1 2]", "a = {", "a = { b", "a = { b =", "a = { b = 1", "a = { b = 1,}", "a = {,}",
+            "a = +", "a = -", "a = _", "a = 0x", "a = 0o", "a = 0b", "a = 0x_", "a = 1_", "a = 1__0", "a = 1e", "a = 1e+",
+            "a = 1.", "a = .1", "a = +0x1", "a = 0b2", "a = 0o8", "a = 0xg", "a = 9223372036854775808", "a = 0x8000000000000000",
+            "a = -9223372036854775809", "a = i", "a = in", "a = +n", "a = nan1", "a = infinity", "a = t", "a = f", "a = tru",
+            "a = 1979-", "a = 1979-05-27T", "a = 1979-05-27T07:", "a = 1979-05-27T07:32:00+", "a = 1979-05-27T07:32:00.",
+            "a = 07:32", "a = 1979-13-01", "a = 1979-02-30", "a = 24:00:00", "a = 1979-05-27 07:32:00Zx", "a = 1\r", "a\r= 1",
+            "a = 1\rb = 2", "\u{0}", "a = \u{0}", "a\u{0} = 1", "\u{FEFF}\u{FEFF}a = 1", "[a]\n[a]", "a = 1\na = 2", "a = 1\n[a]",
+            "[[a]]\n[a]", "a = []\n[[a]]", "a = {}\na.b = 1", "a.b = 1\n[a]", "é", "a = é", "[é]", "a = 1 # \u{7F}", "a = 1 b = 2",
+        ];
+        for toml_string in invalid_documents.iter() {
+            let mut parser = super::Parser::new();
+            assert!(parser.parse(toml_string).is_err(), "{:?} should not parse", toml_string);
+            let mut parser = super::Parser::new();
+            assert!(parser.parse_bytes(toml_string.as_bytes()).is_err(), "{:?} should not parse", toml_string);
+        }
+
+        // Every prefix of a valid document is either valid or an error, parsing must never panic
+        let toml_string = "# comment\n\"q\\u00e9\".b = 'x'\n[t]\na = [1, 2.5e-3, { c = \"\"\"m\nl\"\"\" }]\n[[arr]]\nd = 1979-05-27T07:32:00.5-07:00\ne = -inf\nf = 0xdead\n";
+        for (index, _) in toml_string.char_indices() {
+            let mut parser = super::Parser::new();
+            let _ = parser.parse(&toml_string[..index]);
+        }
+        let mut parser = super::Parser::new();
+        assert!(parser.parse(toml_string).is_ok());
+    }
+
+    #[test]
+    fn test_error_location() {
+        // Each document with the text the error should cover, its line and its column
+        let invalid_documents = [
+            ("a = 1\nb = \"bell\u{7}\"", "bell\u{7}", 2, 6),
+            ("a = 1\n  é = 2", "é", 2, 3),
+            ("a = 1\na = 2", "a", 2, 1),
+            ("[t]\n[t]", "t", 2, 2),
+            ("a = { b = 1, b = 2 }", "b", 1, 14),
+            ("a = 99999999999999999999", "99999999999999999999", 1, 5),
+            ("x = [1,\n2", "[1,\n2", 1, 5),
+            ("\"é\" = \"é\" é", "é", 1, 11),
+            // Errors inside values that span several lines are on the line they were found on
+            ("a = \"x\\q\"", "\\q", 1, 7),
+            ("a = \"\"\"\nx\ny\\q\"\"\"", "\\q", 3, 2),
+            ("a = \"\"\"\nx\u{7}\"\"\"", "x\u{7}", 2, 1),
+            ("a = [\n1,\n2x]", "2", 3, 1),
+            ("a = [\n1,\n2]\na = 3", "a", 4, 1),
+        ];
+        for (toml_string, text, line_number, column) in invalid_documents.iter() {
+            let mut parser = super::Parser::new();
+            let error = parser.parse(toml_string).unwrap_err();
+            assert_eq!(&toml_string[error.span().unwrap()], *text, "{}", toml_string);
+            assert_eq!(error.line_number(), Some(*line_number), "{}", toml_string);
+            assert_eq!(error.column(), Some(*column), "{}", toml_string);
+            // The line in the kind of error is the same line
+            let line = format!("line {}", line_number);
+            assert!(error.kind().to_string().contains(&line), "{}", toml_string);
+        }
+
+        let mut parser = super::Parser::new();
+        let error = parser.parse_bytes(b"a = 1\nb = \"\xFF\"").unwrap_err();
+        assert_eq!(error.span(), Some(11..12));
+        assert_eq!(error.line_number(), Some(2));
+        assert_eq!(error.column(), Some(6));
+
+        let error = super::Error::new(ErrorKind::InvalidValue(1), None);
+        assert_eq!(error.span(), None);
+        assert_eq!(error.line_number(), None);
+        assert_eq!(error.column(), None);
+    }
+
+    #[test]
+    fn test_render_error() {
+        let toml_string = "a = 1\nb = \"abc";
+        let mut parser = super::Parser::new();
+        let error = parser.parse(toml_string).unwrap_err();
+        assert_eq!(
+            error.render(toml_string, false),
+            "error: The value on line 2 is invalid\n \
+             --> line 2, column 6\n  \
+               |\n\
+             2 | b = \"abc\n  \
+               |      ^^^\n  \
+               = expected: a value followed by the end of the line or a comment\n  \
+               = hint: strings and quoted names must be closed with `\"` on the same line\n"
+        );
+
+        // The underline stops at the end of the line and keeps tabs so that it lines up with the text
+        let toml_string = "x = [1,\n2";
+        let mut parser = super::Parser::new();
+        let error = parser.parse(toml_string).unwrap_err();
+        let report = error.render(toml_string, false);
+        assert!(report.contains("1 | x = [1,\n  |     ^^^\n"), "{}", report);
+
+        let toml_string = "\ta = 1__2";
+        let mut parser = super::Parser::new();
+        let report = parser.parse(toml_string).unwrap_err().render(toml_string, false);
+        assert!(report.contains("1 | \ta = 1__2\n  | \t    ^^^\n"), "{}", report);
+
+        // Colour codes are only added when asked for
+        let mut parser = super::Parser::new();
+        let error = parser.parse(toml_string).unwrap_err();
+        let coloured = error.render(toml_string, true);
+        assert!(coloured.contains("\x1b[1;31m^^^\x1b[0m"), "{}", coloured);
+        assert!(!error.render(toml_string, false).contains('\x1b'));
+
+        // Errors that weren't created by the parser have no snippet
+        let error = super::Error::new(ErrorKind::IntegerOverflow(3), None);
+        assert_eq!(
+            error.render("", false),
+            "error: The integer on line 3 does not fit in a 64 bit signed integer\n \
+             = expected: an integer from -9223372036854775808 to 9223372036854775807\n \
+             = hint: use a float or a string for larger numbers\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_all_errors() {
+        let toml_string = "a = 1\nb = 1__2\nc = \"three\"\nd = [1,\n\"e\" = = 5\n[t]\nf = 6\n[t]\ng = 7\n[u]\nh = 8 9\ni = 9";
+        let mut parser = super::Parser::new();
+        let (table, errors) = parser.parse_all_errors(toml_string);
+        let error_kinds: Vec<ErrorKind> = errors.iter().map(|error| error.kind()).collect();
+        assert_eq!(
+            error_kinds,
+            vec![
+                ErrorKind::InvalidUnderscore(2),
+                ErrorKind::InvalidValue(5),
+                ErrorKind::TableRedefined(8),
+                ErrorKind::InvalidValue(11),
+            ]
+        );
+        let error_lines: Vec<Option<usize>> = errors.iter().map(|error| error.line_number()).collect();
+        assert_eq!(error_lines, vec![Some(2), Some(5), Some(8), Some(11)]);
+
+        // Everything that could be read is kept, the values of the table that was redefined are skipped
+        assert_eq!(table.get("a"), Some(&TomlValue::Integer(1)));
+        assert_eq!(table.get("b"), None);
+        assert_eq!(table.get("c"), Some(&TomlValue::String(Cow::Borrowed("three"))));
+        assert_eq!(table.get("d"), None);
+        let mut t = TomlTable::new();
+        t.insert(Cow::Borrowed("f"), TomlValue::Integer(6));
+        assert_eq!(table.get("t"), Some(&TomlValue::Table(t)));
+        // The pair is read before the text after it is found to be invalid
+        let mut u = TomlTable::new();
+        u.insert(Cow::Borrowed("h"), TomlValue::Integer(8));
+        u.insert(Cow::Borrowed("i"), TomlValue::Integer(9));
+        assert_eq!(table.get("u"), Some(&TomlValue::Table(u)));
+        assert_eq!(table.get("g"), None);
+
+        // The values under a table header that couldn't be read are skipped rather than added to the table before it
+        let invalid_headers = [
+            ("[t]\na = 1\n[u\nb = 2\n", ErrorKind::InvalidName(3)),
+            ("a = 1\n[x\nd = 4", ErrorKind::InvalidName(2)),
+            ("a = 1\n[x] y\nd = 4", ErrorKind::InvalidValue(2)),
+            ("a = 1\n[[x\nd = 4", ErrorKind::InvalidName(2)),
+        ];
+        for (toml_string, error_kind) in invalid_headers.iter() {
+            let mut parser = super::Parser::new();
+            let (table, errors) = parser.parse_all_errors(toml_string);
+            let error_kinds: Vec<ErrorKind> = errors.iter().map(|error| error.kind()).collect();
+            assert_eq!(error_kinds, vec![error_kind.clone()], "{}", toml_string);
+            assert_eq!(table.get("b"), None, "{}", toml_string);
+            assert_eq!(table.get("d"), None, "{}", toml_string);
+        }
+        // The pairs of the next table that could be read are kept
+        let toml_string = "[t]\na = 1\n[u\nb = 2\n[v]\nc = 3";
+        let mut parser = super::Parser::new();
+        let (table, errors) = parser.parse_all_errors(toml_string);
+        assert_eq!(errors.len(), 1);
+        let mut t = TomlTable::new();
+        t.insert(Cow::Borrowed("a"), TomlValue::Integer(1));
+        assert_eq!(table.get("t"), Some(&TomlValue::Table(t)));
+        let mut v = TomlTable::new();
+        v.insert(Cow::Borrowed("c"), TomlValue::Integer(3));
+        assert_eq!(table.get("v"), Some(&TomlValue::Table(v)));
+
+        // A pair that is rejected doesn't define the tables along its name
+        let toml_string = "[t.m.n]\n[t]\nm.n.k = 1\n[t.m]\nx = 2";
+        let mut parser = super::Parser::new();
+        let (table, errors) = parser.parse_all_errors(toml_string);
+        let error_kinds: Vec<ErrorKind> = errors.iter().map(|error| error.kind()).collect();
+        assert_eq!(error_kinds, vec![ErrorKind::TableRedefined(3)]);
+        let mut m = TomlTable::new();
+        m.insert(Cow::Borrowed("n"), TomlValue::Table(TomlTable::new()));
+        m.insert(Cow::Borrowed("x"), TomlValue::Integer(2));
+        let mut t = TomlTable::new();
+        t.insert(Cow::Borrowed("m"), TomlValue::Table(m));
+        assert_eq!(table.get("t"), Some(&TomlValue::Table(t)));
+
+        // A valid document gives the same table as parse
+        let toml_string = "a = 1\n[t]\nb = [1, 2]";
+        let mut parser = super::Parser::new();
+        let (table, errors) = parser.parse_all_errors(toml_string);
+        assert!(errors.is_empty());
+        let mut parser = super::Parser::new();
+        assert_eq!(table, parser.parse(toml_string).unwrap());
+    }
+
+    #[test]
+    fn test_error_is_thread_safe() {
+        fn assert_send_sync<T: Send + Sync + 'static>(_: &T) {}
+        let mut parser = super::Parser::new();
+        let error = parser.parse_bytes(b"a = \"\xFF\"").unwrap_err();
+        assert_send_sync(&error);
+
+        // The error can be moved to another thread and boxed like any other error
+        let error = std::thread::spawn(move || error).join().unwrap();
+        let boxed: Box<dyn std::error::Error + Send + Sync> = Box::new(error.clone());
+        assert_eq!(boxed.to_string(), error.to_string());
+    }
+
+    #[test]
+    fn test_error_source() {
+        use std::error::Error;
+
+        let mut parser = super::Parser::new();
+        let error = parser.parse_bytes(b"a = \"\xFF\"").unwrap_err();
+        let source = error.source().expect("The UTF-8 error should be the source");
+        assert!(source.downcast_ref::<std::str::Utf8Error>().is_some());
+
+        let mut parser = super::Parser::new();
+        let error = parser.parse("a = 1x").unwrap_err();
+        assert!(error.source().is_none());
+    }
+
+    #[test]
+    fn test_error_equality() {
+        let mut parser = super::Parser::new();
+        let error = parser.parse("a = 1\nb = 1x").unwrap_err();
+        assert_eq!(error, error.clone());
+
+        // The same kind of error at a different place is not equal
+        let mut parser = super::Parser::new();
+        let other_error = parser.parse("a = 1\nb =  1x").unwrap_err();
+        assert_eq!(error.kind(), other_error.kind());
+        assert_ne!(error, other_error);
+
+        let mut parser = super::Parser::new();
+        let utf8_error = parser.parse_bytes(b"a = \"\xFF\"").unwrap_err();
+        let mut parser = super::Parser::new();
+        assert_eq!(utf8_error, parser.parse_bytes(b"a = \"\xFF\"").unwrap_err());
+        let without_source = super::Error::new(utf8_error.kind(), None);
+        assert_ne!(utf8_error, without_source);
+    }
+
+    #[test]
+    fn test_error_key() {
+        let invalid_documents = [
+            ("[server.tls]\nport = 1__2", "server.tls.port"),
+            ("[server]\ntls = { port = 99999999999999999999 }", "server.tls.port"),
+            ("[server.\"my host\"]\nports = [1, 2x]", "server.\"my host\".ports"),
+            ("[server]\ntls = 1\n[[server.tls]]", "server.tls"),
+            ("[server]\nport = 1\n[server]", "server"),
+            ("[server]\nport = 1\nport = 2", "server.port"),
+            ("[srv]\nt = { b = 1, b = 2 }", "srv.t.b"),
+            // Text after a value is about the pair that was just read
+            ("[server]\nport = 80 80", "server.port"),
+            ("a = \"x\" b", "a"),
+            ("a = truex", "a"),
+            ("\"my key\".b = [1, 2] 3", "\"my key\".b"),
+            ("a = { b = 1 } c", "a"),
+            // Text after a table header is inside the table that was opened
+            ("[a]]", "a"),
+            ("[a.b] x", "a.b"),
+            ("[[u]]\n[[u]] z", "u"),
+        ];
+        for (toml_string, key) in invalid_documents.iter() {
+            let mut parser = super::Parser::new();
+            let error = parser.parse(toml_string).unwrap_err();
+            assert_eq!(error.key(), Some(*key), "{}", toml_string);
+        }
+
+        let mut parser = super::Parser::new();
+        let error = parser.parse("= 1").unwrap_err();
+        assert_eq!(error.key(), None);
+    }
+
+    #[test]
+    fn test_error_file_name() {
+        let toml_string = "[server.tls]\nport = 1__2";
+        let mut parser = super::Parser::new();
+        let error = parser.parse(toml_string).unwrap_err();
+        assert_eq!(error.file_name(), None);
+        let error = error.with_file_name("config.toml");
+        assert_eq!(error.file_name(), Some("config.toml"));
+        assert_eq!(
+            error.to_string(),
+            "Error in TOML Parser in config.toml at server.tls.port, error was The number on line 2 has an underscore that is not between two digits"
+        );
+        assert!(error.render(toml_string, false).contains(" --> config.toml, line 2, column 8\n"));
+    }
+
+    #[test]
+    fn test_owned_document() {
+        let document = {
+            let toml_string = String::from("title = \"owned\"\n[server]\nhosts = [\"a\\tb\", 'c']\n");
+            let mut parser = super::Parser::new();
+            super::Document::from(parser.parse(&toml_string).unwrap())
+        };
+        // The text the document was parsed from has been dropped
+        assert_eq!(document.get("title"), Some(&TomlValue::String(Cow::Borrowed("owned"))));
+        let mut server = TomlTable::new();
+        server.insert(
+            Cow::Borrowed("hosts"),
+            TomlValue::Array(vec![TomlValue::String(Cow::Borrowed("a\tb")), TomlValue::String(Cow::Borrowed("c"))]),
+        );
+        assert_eq!(document.get("server"), Some(&TomlValue::Table(server)));
+
+        let from_str: super::Document = "title = \"owned\"\n[server]\nhosts = [\"a\\tb\", 'c']\n".parse().unwrap();
+        assert_eq!(from_str, document);
+        assert_eq!(from_str.into_table(), document.table().clone());
+
+        let error = "a = ".parse::<super::Document>().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::MissingValue(1));
+    }
+
+    #[test]
+    fn test_document_from_path() {
+        let path = std::env::temp_dir().join(format!("tomlparse_test_{}.toml", std::process::id()));
+        std::fs::write(&path, "a = 1\nb = 1__2\n").unwrap();
+        let error = super::Document::from_path(&path).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidUnderscore(2));
+        assert_eq!(error.file_name(), Some(path.display().to_string().as_str()));
+
+        std::fs::write(&path, "a = 1\n").unwrap();
+        let document = super::Document::from_path(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(document.get("a"), Some(&TomlValue::Integer(1)));
+
+        let error = super::Document::from_path(&path).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Io);
+        assert!(std::error::Error::source(&error).is_some());
+    }
+
+    #[test]
+    fn test_parse_keeps_document_order() {
+        let toml_string = "zebra = 1\napple = 2\nmango.y = 3\nmango.b = 4\n[kiwi]\nz = { d = 1, c = 2 }\na = 5\n[[fig]]\nq = 6\np = 7\n[banana]";
+        let mut parser = super::Parser::new();
+        let table = parser.parse(toml_string).unwrap();
+        let names: Vec<&str> = table.keys().map(|name| name.as_ref()).collect();
+        assert_eq!(names, vec!["zebra", "apple", "mango", "kiwi", "fig", "banana"]);
+
+        let names_of = |value: Option<&TomlValue>| -> Vec<String> {
+            match value {
+                Some(TomlValue::Table(table)) => table.keys().map(|name| name.to_string()).collect(),
+                Some(TomlValue::Array(values)) => match values.first() {
+                    Some(TomlValue::Table(table)) => table.keys().map(|name| name.to_string()).collect(),
+                    _ => Vec::new(),
+                },
+                _ => Vec::new(),
+            }
+        };
+        assert_eq!(names_of(table.get("mango")), vec!["y", "b"]);
+        assert_eq!(names_of(table.get("kiwi")), vec!["z", "a"]);
+        assert_eq!(names_of(table.get("fig")), vec!["q", "p"]);
+        if let Some(TomlValue::Table(kiwi)) = table.get("kiwi") {
+            assert_eq!(names_of(kiwi.get("z")), vec!["d", "c"]);
+        }
+
+        // The document iterates in the same order and can be sorted
+        let mut document = super::Document::from(table);
+        let names: Vec<&str> = document.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["zebra", "apple", "mango", "kiwi", "fig", "banana"]);
+        let unsorted = document.clone();
+        document.sort_keys();
+        let names: Vec<&str> = document.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["apple", "banana", "fig", "kiwi", "mango", "zebra"]);
+        assert_eq!(names_of(document.get("mango")), vec!["b", "y"]);
+        assert_eq!(names_of(document.get("fig")), vec!["p", "q"]);
+        if let Some(TomlValue::Table(kiwi)) = document.get("kiwi") {
+            assert_eq!(names_of(kiwi.get("z")), vec!["c", "d"]);
+        }
+
+        // Order doesn't change equality
+        assert_eq!(document, unsorted);
+    }
 }
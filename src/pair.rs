@@ -1,7 +1,26 @@
+use std::borrow::Cow;
+
 use super::{TomlPair, TomlValue};
 
 impl<'a> TomlPair<'a> {
     pub fn new(name: &'a str, value: TomlValue<'a>) -> TomlPair<'a> {
+        TomlPair {
+            name: vec![Cow::Borrowed(name)],
+            value,
+        }
+    }
+
+    /// Creates a pair with a dotted name, each part of the name is a seperate entry
+    pub fn new_dotted(name: Vec<Cow<'a, str>>, value: TomlValue<'a>) -> TomlPair<'a> {
         TomlPair { name, value }
     }
+
+    /// Each part of the name, quoted parts have had any escapes decoded
+    pub fn name(&self) -> &[Cow<'a, str>] {
+        &self.name
+    }
+
+    pub fn value(&self) -> &TomlValue<'a> {
+        &self.value
+    }
 }
\ No newline at end of file
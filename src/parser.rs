@@ -1,6 +1,17 @@
-use std::{collections::HashMap, mem::replace};
+use std::borrow::Cow;
+use std::mem::take;
 
-use super::{Error, ErrorKind, Parser, ParserState, TomlPair, TomlValue};
+use super::{
+    DocumentBuilder, Error, ErrorKind, LocalDate, LocalDateTime, LocalTime, NamePart,
+    OffsetDateTime, OpenValue, Parser, ParserState, TomlPair, TomlStatement, TomlTable,
+    TomlValue,
+};
+
+impl Default for Parser {
+    fn default() -> Parser {
+        Parser::new()
+    }
+}
 
 impl Parser {
     pub fn new() -> Parser {
@@ -12,6 +23,11 @@ impl Parser {
             name_end: None,
             value_end: None,
             value_start: None,
+            name_parts: Vec::new(),
+            token_start: None,
+            name_span: 0..0,
+            name_line: 1,
+            built_name_parts: Vec::new(),
         }
     }
 
@@ -21,6 +37,10 @@ impl Parser {
             "ASSERT FAILED: Incorrect usage of set name start - name start can only be set once"
         );
         self.name_start = Some(name_start);
+        self.token_start = Some(name_start);
+        if self.name_parts.is_empty() {
+            self.name_line = self.line_number;
+        }
     }
 
     fn set_name_end(&mut self, name_end: usize) {
@@ -37,6 +57,7 @@ impl Parser {
             "ASSERT FAILED: Incorrect usage of set value start - value start can only be set once"
         );
         self.value_start = Some(value_start);
+        self.token_start = Some(value_start);
     }
 
     fn set_value_end(&mut self, value_end: usize) {
@@ -52,7 +73,7 @@ impl Parser {
             self.name_start.is_some(),
             "ASSERT FAILED: Retrieving name start index before it has been set"
         );
-        replace(&mut self.name_start, None).unwrap()
+        self.name_start.take().unwrap()
     }
 
     fn name_end(&mut self) -> usize {
@@ -60,7 +81,7 @@ impl Parser {
             self.name_end.is_some(),
             "ASSERT FAILED: Retrieving name end index before it has been set"
         );
-        replace(&mut self.name_end, None).unwrap()
+        self.name_end.take().unwrap()
     }
 
     fn value_end(&mut self) -> usize {
@@ -68,7 +89,7 @@ impl Parser {
             self.value_end.is_some(),
             "ASSERT FAILED: Retrieving value end index before it has been set"
         );
-        replace(&mut self.value_end, None).unwrap()
+        self.value_end.take().unwrap()
     }
 
     fn value_start(&mut self) -> usize {
@@ -76,190 +97,413 @@ impl Parser {
             self.value_start.is_some(),
             "ASSERT FAILED: Retrieving value start index before it has been set"
         );
-        replace(&mut self.value_start, None).unwrap()
+        self.value_start.take().unwrap()
+    }
+
+    /// Returns the next character and its byte index without consuming it
+    fn peek_char(&self, data_to_parse: &str) -> Option<(usize, char)> {
+        data_to_parse[self.position..]
+            .chars()
+            .next()
+            .map(|char| (self.position, char))
+    }
+
+    /// Returns the next character and its byte index, moving the parser past it
+    fn next_char(&mut self, data_to_parse: &str) -> Option<(usize, char)> {
+        let next = self.peek_char(data_to_parse);
+        if let Some((_, char)) = next {
+            self.position += char.len_utf8();
+        }
+        next
+    }
+
+    pub fn parse<'a>(&mut self, data_to_parse: &'a str) -> Result<TomlTable<'a>, Error> {
+        let mut document = DocumentBuilder::new();
+        while let Some(statement) = self.read_statement(data_to_parse, &document.current_table)? {
+            // The parser hasn't moved past the end of the statement yet so the line number is the line of the statement
+            let result = match statement {
+                TomlStatement::Pair(pair) => document.insert_pair(pair, self.name_line),
+                TomlStatement::Table(table_name) => {
+                    document.open_table(table_name, self.line_number)
+                }
+                TomlStatement::ArrayOfTables(table_name) => {
+                    document.open_array_of_tables(table_name, self.line_number)
+                }
+            };
+            // Errors from the document are about the name of the statement, the document gives them its full key
+            result.map_err(|error| error.with_span(self.name_span.clone()).locate(data_to_parse))?;
+        }
+        Ok(document.into_table())
+    }
+
+    /// Parses the whole document even if it contains errors, every error is returned along with the values that could be read
+    /// After an error the parser starts again on the next line, the values under a table header that couldn't be read or opened are skipped
+    /// An error inside an array that spans several lines can cause more errors, as the lines after it are read as new statements
+    pub fn parse_all_errors<'a>(&mut self, data_to_parse: &'a str) -> (TomlTable<'a>, Vec<Error>) {
+        let mut document = DocumentBuilder::new();
+        let mut errors = Vec::new();
+        // Values after a table header that couldn't be read or opened would be added to the wrong table
+        let mut skipping_table = false;
+        loop {
+            let statement_start = self.position;
+            let statement = match self.read_statement(data_to_parse, &document.current_table) {
+                Ok(Some(statement)) => statement,
+                Ok(None) => break,
+                Err(error) => {
+                    errors.push(error);
+                    // The state is checked before it is reset by recovering
+                    if matches!(
+                        self.state,
+                        ParserState::BeforeTableName(_)
+                            | ParserState::ReadingTableName(_)
+                            | ParserState::AfterTableName(_)
+                            | ParserState::FinishedTable(_)
+                    ) {
+                        skipping_table = true;
+                    }
+                    self.recover(data_to_parse, statement_start);
+                    continue;
+                }
+            };
+            let result = match statement {
+                TomlStatement::Pair(_) if skipping_table => continue,
+                TomlStatement::Pair(pair) => document.insert_pair(pair, self.name_line),
+                TomlStatement::Table(table_name) => {
+                    let result = document.open_table(table_name, self.line_number);
+                    skipping_table = result.is_err();
+                    result
+                }
+                TomlStatement::ArrayOfTables(table_name) => {
+                    let result = document.open_array_of_tables(table_name, self.line_number);
+                    skipping_table = result.is_err();
+                    result
+                }
+            };
+            if let Err(error) = result {
+                errors.push(error.with_span(self.name_span.clone()).locate(data_to_parse));
+            }
+        }
+        (document.into_table(), errors)
+    }
+
+    /// Moves the parser to the start of the line after an error so that it can carry on reading the document
+    fn recover(&mut self, data_to_parse: &str, statement_start: usize) {
+        // The new line that caused the error may have already been read, otherwise the rest of the line is skipped
+        let at_line_start =
+            self.position > statement_start && data_to_parse[..self.position].ends_with('\n');
+        if !at_line_start {
+            self.position = match data_to_parse[self.position..].find('\n') {
+                Some(index) => self.position + index + 1,
+                None => data_to_parse.len(),
+            };
+        }
+        self.line_number = data_to_parse[..self.position].matches('\n').count() + 1;
+        self.state = ParserState::Normal;
+        self.name_start = None;
+        self.name_end = None;
+        self.value_start = None;
+        self.value_end = None;
+        self.name_parts.clear();
+        self.token_start = None;
+    }
+
+    /// Parses a document that has not been checked to be UTF-8 yet, ie the contents of a file
+    pub fn parse_bytes<'a>(&mut self, data_to_parse: &'a [u8]) -> Result<TomlTable<'a>, Error> {
+        match std::str::from_utf8(data_to_parse) {
+            Ok(data_to_parse) => self.parse(data_to_parse),
+            Err(error) => {
+                // Report the line that the invalid bytes are on
+                let valid_up_to = error.valid_up_to();
+                let valid_data = &data_to_parse[..valid_up_to];
+                let line_number = valid_data.iter().filter(|byte| **byte == b'\n').count() + 1;
+                let span = valid_up_to..valid_up_to + error.error_len().unwrap_or(data_to_parse.len() - valid_up_to);
+                Err(Error::new(
+                    ErrorKind::InvalidUtf8(line_number),
+                    Some(Box::new(error)),
+                )
+                .with_span(span)
+                .locate(std::str::from_utf8(valid_data).unwrap_or_default()))
+            }
+        }
     }
 
-    pub fn parse<'a>(
+    /// Wrapper around read_statement that is used in testing
+    #[cfg(test)]
+    pub(crate) fn read_test_statement<'a>(
         &mut self,
         data_to_parse: &'a str,
-    ) -> Result<HashMap<&'a str, TomlValue<'a>>, Error> {
-        let mut hs = HashMap::new();
-        while let Some(pair) = self.read_pair(data_to_parse)? {
-            // Add it to the hashmap
-            hs.insert(pair.name, pair.value);
-        }
-        Ok(hs)
+    ) -> Result<Option<TomlStatement<'a>>, Error> {
+        let statement = self.read_statement(data_to_parse, &[])?;
+        Ok(statement)
     }
 
-    /// Wrapper around read_pair that is used in testing
+    /// Wrapper around read_statement that is used in testing, panics if the statement is not a name value pair
     #[cfg(test)]
     pub(crate) fn read_test_pair<'a>(
         &mut self,
         data_to_parse: &'a str,
     ) -> Result<Option<TomlPair<'a>>, Error> {
-        let pair = self.read_pair(data_to_parse)?;
-        Ok(pair)
+        match self.read_statement(data_to_parse, &[])? {
+            Some(TomlStatement::Pair(pair)) => Ok(Some(pair)),
+            Some(statement) => panic!("Expected a name value pair but read {:?}", statement),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the next TOML statement, any error is given the location of the text that caused it and the name being read
+    /// The current table is the table that names in the statement are inside of
+    fn read_statement<'a>(
+        &mut self,
+        data_to_parse: &'a str,
+        current_table: &[Cow<'a, str>],
+    ) -> Result<Option<TomlStatement<'a>>, Error> {
+        // Arrays and inline tables that have been started but not finished, the innermost value is last
+        let mut open_values = Vec::new();
+        let statement = self.read_next_statement(data_to_parse, &mut open_values);
+        statement.map_err(|error| {
+            // The error covers the name or value being read, otherwise it is the last character that was read
+            // A value that has run onto later lines isn't covered so that the error stays on the line it was found on
+            let start = match self.token_start {
+                Some(token_start)
+                    if token_start < self.position
+                        && !data_to_parse[token_start..self.position].contains('\n') =>
+                {
+                    token_start
+                }
+                _ => data_to_parse[..self.position]
+                    .char_indices()
+                    .next_back()
+                    .map_or(self.position, |(index, _)| index),
+            };
+            // A table header names a table from the root of the document, other names are inside the current table
+            let mut key: Vec<Cow<str>> = match self.state {
+                ParserState::BeforeTableName(_)
+                | ParserState::ReadingTableName(_)
+                | ParserState::AfterTableName(_)
+                | ParserState::FinishedTable(_) => Vec::new(),
+                _ => current_table.to_vec(),
+            };
+            // The name of a pair has been built by the time the text after its value is checked
+            if matches!(self.state, ParserState::AfterValue) {
+                key.extend(Parser::name_text(data_to_parse, &self.built_name_parts));
+            }
+            for open_value in open_values.iter() {
+                let name = match open_value {
+                    OpenValue::Array { name, .. } => name,
+                    OpenValue::InlineTable { name, .. } => name,
+                };
+                key.extend(Parser::name_text(data_to_parse, name));
+            }
+            // Errors from adding a pair to an inline table have a key that starts from the inline table
+            let error = error.with_key_prefix(&key);
+            key.extend(Parser::name_text(data_to_parse, &self.name_parts));
+            error
+                .with_span(start..self.position)
+                .with_key(&key)
+                .locate(data_to_parse)
+        })
+    }
+
+    /// The text of each part of a name, escapes are decoded if they are valid
+    fn name_text<'a>(data_to_parse: &'a str, name_parts: &[NamePart]) -> Vec<Cow<'a, str>> {
+        name_parts
+            .iter()
+            .map(|(name_start, name_end, is_basic_string)| {
+                let name = &data_to_parse[*name_start..*name_end];
+                match is_basic_string {
+                    true => Parser::unescape_basic_string(name, *name_start, 0)
+                        .unwrap_or(Cow::Borrowed(name)),
+                    false => Cow::Borrowed(name),
+                }
+            })
+            .collect()
     }
 
     // TODO: Convert to stream to allow file io while parsing
     /// Returns the next TOML statement, returns none if there are no more lines
-    fn read_pair<'a>(&mut self, data_to_parse: &'a str) -> Result<Option<TomlPair<'a>>, Error> {
+    fn read_next_statement<'a>(
+        &mut self,
+        data_to_parse: &'a str,
+        open_values: &mut Vec<OpenValue<'a>>,
+    ) -> Result<Option<TomlStatement<'a>>, Error> {
+        // A byte order mark at the start of the input is not part of the document
+        if self.position == 0 && data_to_parse.starts_with('\u{FEFF}') {
+            self.position = '\u{FEFF}'.len_utf8();
+        }
         // Take the current position and read the next name value pair
-        // The name ends after we read an equal ?
         // We treat it as a state machine - ie initial state reading a name, then reading a value
-        // A line must contain a = unless it is a multiline value but we process them in one go
-        // This is not correct, a line may begin with whitespace
+        // Every state reads from self.position so the position is always the next character that has not been processed
         if data_to_parse.len() == self.position {
-            println!("End of buffer");
+            trace!("End of buffer");
             return Ok(None);
         }
 
-        let mut sequence = data_to_parse[self.position..].chars().enumerate();
-        println!(
-            "Remaining string to parse is {}",
-            &data_to_parse[self.position..]
-        );
-
-        println!(
-            "Pointing at character {:?}",
-            &data_to_parse[self.position..]
+        // Only positions are traced, the document itself may contain secrets
+        trace!(
+            "Reading statement at byte {} on line {}",
+            self.position,
+            self.line_number
         );
 
         loop {
             match self.state {
                 ParserState::Normal => {
                     // Normal state means we are ready to accept a new name value pair or the end of the file
-                    self.process_normal_state(&mut sequence)?;
+                    self.process_normal_state(data_to_parse)?;
+                }
+                ParserState::BeforeTableName(is_array) => {
+                    self.process_before_table_name_state(data_to_parse, is_array)?;
+                }
+                ParserState::ReadingTableName(is_array) => {
+                    self.process_reading_table_name_state(data_to_parse, is_array)?;
+                }
+                ParserState::AfterTableName(is_array) => {
+                    self.process_after_table_name_state(data_to_parse, is_array)?;
+                }
+                ParserState::FinishedTable(is_array) => {
+                    let table = self.build_table_statement(data_to_parse, is_array)?;
+                    // Only whitespace or a comment may follow a table header
+                    self.state = ParserState::AfterValue;
+                    return Ok(Some(table));
                 }
                 ParserState::ReadingName => {
-                    self.process_reading_name_state(&mut sequence)?;
+                    self.process_reading_name_state(data_to_parse)?;
+                }
+                ParserState::BeforeNamePart => {
+                    self.process_before_name_part_state(data_to_parse)?;
                 }
                 ParserState::BeforeEquals => {
-                    self.process_before_equals_state(&mut sequence)?;
+                    self.process_before_equals_state(data_to_parse)?;
                 }
                 ParserState::AfterEquals => {
-                    self.process_after_equals_state(&mut sequence)?;
+                    self.process_after_equals_state(data_to_parse)?;
+                }
+                ParserState::StartOfValue => {
+                    self.process_start_of_value_state(data_to_parse, open_values)?;
+                }
+                ParserState::BeforeArrayValue => {
+                    if let Some(pair) =
+                        self.process_before_array_value_state(data_to_parse, open_values)?
+                    {
+                        return Ok(Some(TomlStatement::Pair(pair)));
+                    }
+                }
+                ParserState::AfterArrayValue => {
+                    if let Some(pair) =
+                        self.process_after_array_value_state(data_to_parse, open_values)?
+                    {
+                        return Ok(Some(TomlStatement::Pair(pair)));
+                    }
+                }
+                ParserState::BeforeInlineTableName => {
+                    if let Some(pair) =
+                        self.process_before_inline_table_name_state(data_to_parse, open_values)?
+                    {
+                        return Ok(Some(TomlStatement::Pair(pair)));
+                    }
+                }
+                ParserState::AfterInlineTableValue => {
+                    if let Some(pair) =
+                        self.process_after_inline_table_value_state(data_to_parse, open_values)?
+                    {
+                        return Ok(Some(TomlStatement::Pair(pair)));
+                    }
                 }
                 ParserState::ReadingInteger => {
                     // This state can move to ReadingFloat anytime we see a .
-                    self.process_read_integer_state(&mut sequence, data_to_parse)?;
-                    // return Ok(Some(value));
+                    self.process_read_integer_state(data_to_parse)?;
                 }
                 ParserState::ReadingFloat(after_exponent) => {
-                    self.process_read_float_state(&mut sequence, data_to_parse, after_exponent)?;
+                    self.process_read_float_state(data_to_parse, after_exponent)?;
                 }
                 ParserState::ReadingString => {
-                    self.process_read_string_state(&mut sequence)?;
-                    // return Ok(Some(string_value));
+                    self.process_read_string_state(data_to_parse)?;
+                }
+                ParserState::ReadingLiteralString => {
+                    self.process_read_literal_string_state(data_to_parse)?;
+                }
+                ParserState::ReadingMultiLineString(is_basic_string) => {
+                    self.process_read_multi_line_string_state(data_to_parse, is_basic_string)?;
+                }
+                ParserState::ReadingDateTime(value_start) => {
+                    self.process_read_date_time_state(data_to_parse, value_start)?;
                 }
                 ParserState::AfterValue => {
                     // This state means we have read in a name value pair but we have not seen a new line that would indicate the start of a potential new name value pair
-                    // self.state.next()
-                    self.process_after_value_state(&mut sequence)?;
+                    self.process_after_value_state(data_to_parse)?;
                 }
                 ParserState::ReadingTrue(index) => {
                     self.process_reading_true_state(index, data_to_parse)?
                 }
+                ParserState::ReadingSpecialFloat(index) => {
+                    self.process_reading_special_float_state(index, data_to_parse)?;
+                }
                 ParserState::ReadingFalse(index) => {
                     self.process_reading_false_state(index, data_to_parse)?
                 }
-                // Add states building BuildingString, BuildingInteger, BuildingBoolean etc these are the final states, once this state is reached the pair has been read
-                ParserState::FinishedFloat(eof, new_line) => {
-                    let float_pair = self.build_float_pair(data_to_parse)?;
-                    match (eof, new_line) {
-                        (true, false) => self.state = ParserState::EndOfFile,
-                        
-                        (false, true) => self.state = ParserState::Normal,
-                        (false, false) => self.state = ParserState::AfterValue,
-                        (true, true) => unreachable!("Both the new line and eof were marked when entering the FinishedInteger State, this should be impossible"),
+                // The finished states build the value, the character that ended the value has not been consumed yet
+                ParserState::FinishedFloat => {
+                    let value = self.build_float_value(data_to_parse)?;
+                    if let Some(pair) = self.finish_value(data_to_parse, value, open_values)? {
+                        return Ok(Some(TomlStatement::Pair(pair)));
                     }
-                    return Ok(Some(float_pair));
                 }
                 ParserState::FinishedBoolean(bool_value) => {
-                    let pair = self.build_bool_pair(bool_value, data_to_parse)?;
-                    self.state = ParserState::AfterValue;
-                    return Ok(Some(pair));
-                }
-                ParserState::FinishedInteger(eof, eol) => {
-                    let int_pair = self.build_integer_pair(data_to_parse)?;
-                    match (eof, eol) {
-                        // Eof after reading the integer
-                        (true, false) => self.state = ParserState::EndOfFile,
-                        // We can proceed directly to normal state since we already saw a new line
-                        (false, true) => self.state = ParserState::Normal,
-                        (false, false) => self.state = ParserState::AfterValue,
-                        (true, true) => unreachable!("Both the new line and eof were marked when entering the FinishedInteger State, this should be impossible"),
-                        
-                    }
-                    return Ok(Some(int_pair));
-                }
-                ParserState::FinishedNegativeInteger(eof, eol) => {
-                    let int_pair = self.build_negative_integer_pair(data_to_parse)?;
-                    match (eof, eol) {
-                        // Eof after reading the integer
-                        (true, false) => self.state = ParserState::EndOfFile,
-                        // We can proceed directly to normal state since we already saw a new line
-                        (false, true) => self.state = ParserState::Normal,
-                        (false, false) => self.state = ParserState::AfterValue,
-                        (true, true) => unreachable!("Both the new line and eof were marked when entering the FinishedInteger State, this should be impossible"),
-                        
-                    }
-                    return Ok(Some(int_pair));
+                    let value = self.build_bool_value(bool_value);
+                    if let Some(pair) = self.finish_value(data_to_parse, value, open_values)? {
+                        return Ok(Some(TomlStatement::Pair(pair)));
+                    }
+                }
+                ParserState::FinishedInteger => {
+                    let value = self.build_integer_value(data_to_parse)?;
+                    if let Some(pair) = self.finish_value(data_to_parse, value, open_values)? {
+                        return Ok(Some(TomlStatement::Pair(pair)));
+                    }
                 }
                 ParserState::ReadingOctalInteger => {
-                    self.process_reading_octal(&mut sequence, data_to_parse)?;
+                    self.process_reading_octal(data_to_parse)?;
                 }
                 ParserState::ReadingBinaryInteger => {
-                    self.process_reading_binary(&mut sequence, data_to_parse)?;
-                },
-                ParserState::ReadingNegativeInteger => {
-                    self.process_negative_integer(&mut sequence, data_to_parse)?;
-                }
-                ParserState::FinishedBinaryInteger(eof, eol) => {
-                    let int_pair = self.build_binary_integer_pair(data_to_parse)?;
-                    match (eof, eol) {
-                        // Eof after reading the integer
-                        (true, false) => self.state = ParserState::EndOfFile,
-                        // We can proceed directly to normal state since we already saw a new line
-                        (false, true) => self.state = ParserState::Normal,
-                        (false, false) => self.state = ParserState::AfterValue,
-                        (true, true) => unreachable!("Both the new line and eof were marked when entering the FinishedInteger State, this should be impossible"),
-                        
-                    }
-                    return Ok(Some(int_pair));
-                },
-                ParserState::FinishedHexInteger(eof, eol) => {
-                    let int_pair = self.build_hex_integer_pair(data_to_parse)?;
-                    match (eof, eol) {
-                        // Eof after reading the integer
-                        (true, false) => self.state = ParserState::EndOfFile,
-                        // We can proceed directly to normal state since we already saw a new line
-                        (false, true) => self.state = ParserState::Normal,
-                        (false, false) => self.state = ParserState::AfterValue,
-                        (true, true) => unreachable!("Both the new line and eof were marked when entering the FinishedInteger State, this should be impossible"),
-                    }
-                    return Ok(Some(int_pair));
-                },
-                ParserState::FinishedOctalInteger(eof, eol) => {
-                    let int_pair = self.build_octal_integer_pair(data_to_parse)?;
-                    match (eof, eol) {
-                        // Eof after reading the integer
-                        (true, false) => self.state = ParserState::EndOfFile,
-                        // We can proceed directly to normal state since we already saw a new line
-                        (false, true) => self.state = ParserState::Normal,
-                        (false, false) => self.state = ParserState::AfterValue,
-                        (true, true) => unreachable!("Both the new line and eof were marked when entering the FinishedInteger State, this should be impossible"),
-                        
-                    }
-                    return Ok(Some(int_pair));
-                },
+                    self.process_reading_binary(data_to_parse)?;
+                }
+                ParserState::FinishedBinaryInteger => {
+                    let value = self.build_binary_integer_value(data_to_parse)?;
+                    if let Some(pair) = self.finish_value(data_to_parse, value, open_values)? {
+                        return Ok(Some(TomlStatement::Pair(pair)));
+                    }
+                }
+                ParserState::FinishedHexInteger => {
+                    let value = self.build_hex_integer_value(data_to_parse)?;
+                    if let Some(pair) = self.finish_value(data_to_parse, value, open_values)? {
+                        return Ok(Some(TomlStatement::Pair(pair)));
+                    }
+                }
+                ParserState::FinishedOctalInteger => {
+                    let value = self.build_octal_integer_value(data_to_parse)?;
+                    if let Some(pair) = self.finish_value(data_to_parse, value, open_values)? {
+                        return Ok(Some(TomlStatement::Pair(pair)));
+                    }
+                }
                 ParserState::ReadingHexInteger => {
-                    self.process_reading_hex(&mut sequence, data_to_parse)?;
+                    self.process_reading_hex(data_to_parse)?;
                 }
                 ParserState::FinishedString => {
-                    let string_pair = self.build_string_pair(data_to_parse)?;
-                    self.state = ParserState::AfterValue;
-                    return Ok(Some(string_pair));
+                    let value = self.build_string_value(data_to_parse)?;
+                    if let Some(pair) = self.finish_value(data_to_parse, value, open_values)? {
+                        return Ok(Some(TomlStatement::Pair(pair)));
+                    }
+                }
+                ParserState::FinishedLiteralString => {
+                    let value = self.build_literal_string_value(data_to_parse);
+                    if let Some(pair) = self.finish_value(data_to_parse, value, open_values)? {
+                        return Ok(Some(TomlStatement::Pair(pair)));
+                    }
+                }
+                ParserState::FinishedDateTime => {
+                    let value = self.build_date_time_value(data_to_parse)?;
+                    if let Some(pair) = self.finish_value(data_to_parse, value, open_values)? {
+                        return Ok(Some(TomlStatement::Pair(pair)));
+                    }
                 }
                 ParserState::EndOfFile => {
                     return Ok(None);
@@ -268,1161 +512,1756 @@ impl Parser {
         }
     }
 
-    fn process_reading_true_state<'a>(
+    /// Returns true if the character marks the end of a number, the character is left for the state after the value to process
+    fn is_value_terminator(char: char) -> bool {
+        matches!(char, ' ' | '\t' | '\n' | '\r' | '#' | ',' | ']' | '}')
+    }
+
+    /// Called once a value has been read, the value is either added to the array that contains it or it completes the name value pair
+    fn finish_value<'a>(
+        &mut self,
+        data_to_parse: &'a str,
+        value: TomlValue<'a>,
+        open_values: &mut Vec<OpenValue<'a>>,
+    ) -> Result<Option<TomlPair<'a>>, Error> {
+        // The value has been built so later errors are not about it
+        self.token_start = None;
+        match open_values.last_mut() {
+            Some(OpenValue::Array { values, .. }) => {
+                values.push(value);
+                self.state = ParserState::AfterArrayValue;
+                Ok(None)
+            }
+            Some(OpenValue::InlineTable { table, .. }) => {
+                let pair = TomlPair::new_dotted(self.build_name(data_to_parse)?, value);
+                table
+                    .insert_pair(pair, self.name_line)
+                    .map_err(|error| error.with_span(self.name_span.clone()))?;
+                self.state = ParserState::AfterInlineTableValue;
+                Ok(None)
+            }
+            None => {
+                let pair = TomlPair::new_dotted(self.build_name(data_to_parse)?, value);
+                self.state = ParserState::AfterValue;
+                Ok(Some(pair))
+            }
+        }
+    }
+
+    /// Called after seeing a ] or a }, the innermost array or inline table is finished
+    fn finish_open_value<'a>(
         &mut self,
-        start_index: usize,
         data_to_parse: &'a str,
+        open_values: &mut Vec<OpenValue<'a>>,
+    ) -> Result<Option<TomlPair<'a>>, Error> {
+        let (value, name, line_number) = match open_values.pop() {
+            Some(OpenValue::Array {
+                values,
+                name,
+                line_number,
+                ..
+            }) => (TomlValue::Array(values), name, line_number),
+            Some(OpenValue::InlineTable {
+                table,
+                name,
+                line_number,
+            }) => (TomlValue::Table(table.into_table()), name, line_number),
+            None => {
+                // The array and inline table states are only reached after a value has been opened so this should never happen
+                return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
+            }
+        };
+        // Restore the name that was waiting for this value, the name is on the line that the value started on
+        self.name_parts = name;
+        self.name_line = line_number;
+        self.finish_value(data_to_parse, value, open_values)
+    }
+
+    /// Control characters other than tab are not permitted in comments, names or strings
+    fn is_invalid_control_character(char: char) -> bool {
+        matches!(char, '\u{0}'..='\u{8}' | '\u{A}'..='\u{1F}' | '\u{7F}')
+    }
+
+    /// Names that are not quoted can only contain A-Z, a-z, 0-9, _ and -
+    fn is_bare_key_character(char: char) -> bool {
+        char.is_ascii_alphanumeric() || char == '_' || char == '-'
+    }
+
+    /// Called after seeing a \r, the only valid line ending that starts with a \r is \r\n
+    fn process_carriage_return(&mut self, data_to_parse: &str) -> Result<(), Error> {
+        if let Some((_, '\n')) = self.next_char(data_to_parse) {
+            self.line_number += 1;
+            return Ok(());
+        }
+        Err(Error::new(
+            ErrorKind::InvalidEndOfLine(self.line_number),
+            None,
+        ))
+    }
+
+    fn process_reading_true_state(
+        &mut self,
+        start_index: usize,
+        data_to_parse: &str,
     ) -> Result<(), Error> {
         // data_to_parse[value_start..value_start + 4] == true
-        // else invalid value
-        if data_to_parse.len() < start_index + 4 {
-            // Buffer is not long enough to contain the true so its an invalid value
-            return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
-        }
-        match &data_to_parse[start_index..start_index + 4] {
-            "true" => self.set_value_end(start_index + 4),
+        // else invalid value, this includes the buffer not being long enough to contain the true
+        match data_to_parse.get(start_index..start_index + 4) {
+            Some("true") => self.set_value_end(start_index + 4),
             _ => return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None)),
         }
-        self.position += start_index + 4;
+        self.position = start_index + 4;
         self.state = ParserState::FinishedBoolean(true);
         Ok(())
     }
 
-    fn process_reading_false_state<'a>(
+    fn process_reading_special_float_state(
         &mut self,
         start_index: usize,
-        data_to_parse: &'a str,
+        data_to_parse: &str,
     ) -> Result<(), Error> {
-        // data_to_parse[value_start..value_start + 5] == false
-        // else invalid value
-        if data_to_parse.len() < start_index + 5 {
-            // Buffer is not long enough to contain the true so its an invalid value
-            return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
-        }
-        match &data_to_parse[start_index..start_index + 5] {
-            "false" => self.set_value_end(start_index + 5),
+        // inf and nan can be signed, ie -inf
+        let keyword_start = match data_to_parse[start_index..].starts_with(['+', '-']) {
+            true => start_index + 1,
+            false => start_index,
+        };
+        match data_to_parse.get(keyword_start..keyword_start + 3) {
+            Some("inf") | Some("nan") => {}
             _ => return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None)),
         }
-        self.position += start_index + 5;
-        self.state = ParserState::FinishedBoolean(false);
+        self.set_value_start(start_index);
+        self.set_value_end(keyword_start + 3);
+        self.position = keyword_start + 3;
+        self.state = ParserState::FinishedFloat;
         Ok(())
     }
 
-    fn process_after_value_state(
+    fn process_reading_false_state(
         &mut self,
-        sequence: &mut std::iter::Enumerate<std::str::Chars>,
+        start_index: usize,
+        data_to_parse: &str,
     ) -> Result<(), Error> {
-        match sequence.next() {
+        // data_to_parse[value_start..value_start + 5] == false
+        // else invalid value, this includes the buffer not being long enough to contain the false
+        match data_to_parse.get(start_index..start_index + 5) {
+            Some("false") => self.set_value_end(start_index + 5),
+            _ => return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None)),
+        }
+        self.position = start_index + 5;
+        self.state = ParserState::FinishedBoolean(false);
+        Ok(())
+    }
+
+    fn process_after_value_state(&mut self, data_to_parse: &str) -> Result<(), Error> {
+        match self.next_char(data_to_parse) {
             Some((_, char)) => match char {
                 ' ' | '\t' => {
-                    println!("Whitespace after a value");
-                    return Ok(());
+                    trace!("Whitespace after a value");
+                    Ok(())
                 }
                 '#' => {
-                    // Comment after value is valid
-                    // self.transition_to(ParserState::Comment)
-                    unimplemented!("Comments are not done");
+                    // Comment after value is valid, the comment runs until the end of the line
+                    self.read_comment(data_to_parse)?;
+                    self.state = ParserState::Normal;
+                    Ok(())
                 }
                 '\n' => {
                     // Whitespace - Move to NewLine state
                     self.line_number += 1;
                     self.state = ParserState::Normal;
-                    return Ok(());
+                    Ok(())
                 }
                 '\r' => {
-                    // Next character must be \n and then return pair
-                    if let Some((_, '\n')) = sequence.next() {
-                        // No Op continue looking for the start of a name
-                        self.line_number += 1;
-                        self.state = ParserState::Normal;
-                        return Ok(());
-                    } else {
-                        return Err(Error::new(
-                            ErrorKind::InvalidEndOfLine(self.line_number),
-                            None,
-                        ));
-                    }
+                    // Next character must be \n
+                    self.process_carriage_return(data_to_parse)?;
+                    self.state = ParserState::Normal;
+                    Ok(())
                 }
                 _ => {
                     // Error invalid value - started a new value or name on the same line as a completed name/value
-                    return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
+                    Err(Error::new(ErrorKind::InvalidValue(self.line_number), None))
                 }
             },
             None => {
-                // File ended with a new line
-                println!("File ended with a new line");
+                // File ended after the value
+                trace!("File ended after a value");
                 self.state = ParserState::EndOfFile;
-                return Ok(());
+                Ok(())
             }
         }
     }
 
-    // Processes the state after reading a pair, returns Some(()) if a name was found otherwise returns none
-    // Returns Some(()) if we didn't reach the end of the file
-    fn process_normal_state(
-        &mut self,
-        sequence: &mut std::iter::Enumerate<std::str::Chars>,
-    ) -> Result<(), Error> {
-        match sequence.next() {
+    /// Reads a comment until the end of the line including the new line, a comment may contain any character other than a control character
+    fn read_comment(&mut self, data_to_parse: &str) -> Result<(), Error> {
+        loop {
+            match self.next_char(data_to_parse) {
+                Some((_, char)) => match char {
+                    '\n' => {
+                        self.line_number += 1;
+                        return Ok(());
+                    }
+                    '\r' => {
+                        self.process_carriage_return(data_to_parse)?;
+                        return Ok(());
+                    }
+                    char if Parser::is_invalid_control_character(char) => {
+                        return Err(Error::new(ErrorKind::InvalidComment(self.line_number), None));
+                    }
+                    _ => {
+                        // No Op - everything else is part of the comment
+                    }
+                },
+                None => {
+                    // File ended with a comment
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    // Processes the state after reading a pair, moves to ReadingName if the start of a name was found
+    fn process_normal_state(&mut self, data_to_parse: &str) -> Result<(), Error> {
+        match self.next_char(data_to_parse) {
             Some((index, char)) => match char {
                 ' ' | '\t' => {
-                    println!("Whitespace on a new line");
-                    return Ok(());
+                    trace!("Whitespace on a new line");
+                    Ok(())
                 }
                 '#' => {
                     // Comment, we scan until the end of the line
-                    // self.transition_to(ParserState::CommentLine);
-                    unimplemented!("Support for comments not done");
+                    self.read_comment(data_to_parse)?;
+                    Ok(())
                 }
                 '\n' => {
                     // Whitespace - no op
                     self.line_number += 1;
-                    return Ok(());
+                    Ok(())
                 }
                 '\r' => {
-                    // Next character must be \n and then return pair
-                    if let Some((_, '\n')) = sequence.next() {
-                        // No Op continue looking for the start of a name
-                        self.line_number += 1;
-                        return Ok(());
+                    // Next character must be \n, no op continue looking for the start of a name
+                    self.process_carriage_return(data_to_parse)?;
+                    Ok(())
+                }
+                '[' => {
+                    // Start of a table header, [[ starts an array of tables header
+                    if let Some((_, '[')) = self.peek_char(data_to_parse) {
+                        self.next_char(data_to_parse);
+                        self.state = ParserState::BeforeTableName(true);
                     } else {
-                        return Err(Error::new(
-                            ErrorKind::InvalidEndOfLine(self.line_number),
-                            None,
-                        ));
+                        self.state = ParserState::BeforeTableName(false);
                     }
+                    Ok(())
                 }
-                '"' => {
-                    unimplemented!("String names are not supported yet");
-                    // self.transition_to(ParserState::ReadingStringName);
-                    // self.state = ParserState::ReadingStringName;
-                    //self.set_name_start(index + self.position + 1);
-                    //return Ok(Some(()));
+                '"' | '\'' => {
+                    // Quoted name, ie "127.0.0.1" = "localhost"
+                    self.read_quoted_name(data_to_parse, char)?;
+                    self.state = ParserState::BeforeEquals;
+                    Ok(())
                 }
-                _ => {
+                '.' | '=' => {
+                    // A name can't start with a . or be empty
+                    Err(Error::new(ErrorKind::InvalidName(self.line_number), None))
+                }
+                char if Parser::is_bare_key_character(char) => {
                     self.state = ParserState::ReadingName;
-                    self.set_name_start(index + self.position);
-                    println!("Starting reading name with {} - {:?}", char, char);
-                    return Ok(());
+                    self.set_name_start(index);
+                    trace!("Starting reading name at {}", index);
+                    Ok(())
+                }
+                _ => {
+                    // A bare name can only contain A-Z, a-z, 0-9, _ and -
+                    Err(Error::new(ErrorKind::InvalidBareKey(self.line_number), None))
                 }
             },
             None => {
                 // File ended with a new line
-                println!("File ended with a new line");
+                trace!("File ended with a new line");
                 // Change state to end of file
                 self.state = ParserState::EndOfFile;
-                return Ok(());
+                Ok(())
             }
         }
     }
 
-    fn process_reading_name_state(
+    fn process_before_table_name_state(
         &mut self,
-        sequence: &mut std::iter::Enumerate<std::str::Chars>,
+        data_to_parse: &str,
+        is_array: bool,
     ) -> Result<(), Error> {
         loop {
-            match sequence.next() {
+            match self.next_char(data_to_parse) {
                 Some((index, char)) => match char {
-                    '=' => {
-                        self.state = ParserState::AfterEquals;
-                        self.set_name_end(index + self.position);
-                        // *name_ends = index + self.position;
+                    ' ' | '\t' => {
+                        // No Op whitespace is allowed around each part of the table name
+                    }
+                    '.' | ']' | '[' | '#' | '\n' | '\r' => {
+                        // Each part of a table name must contain at least one character
+                        return Err(Error::new(ErrorKind::InvalidName(self.line_number), None));
+                    }
+                    '"' | '\'' => {
+                        self.read_quoted_name(data_to_parse, char)?;
+                        self.state = ParserState::AfterTableName(is_array);
                         return Ok(());
                     }
+                    char if Parser::is_bare_key_character(char) => {
+                        self.state = ParserState::ReadingTableName(is_array);
+                        self.set_name_start(index);
+                        return Ok(());
+                    }
+                    _ => {
+                        return Err(Error::new(ErrorKind::InvalidBareKey(self.line_number), None));
+                    }
+                },
+                None => {
+                    // File ended before the table header was closed
+                    return Err(Error::new(ErrorKind::InvalidName(self.line_number), None));
+                }
+            }
+        }
+    }
+
+    fn process_reading_table_name_state(
+        &mut self,
+        data_to_parse: &str,
+        is_array: bool,
+    ) -> Result<(), Error> {
+        loop {
+            match self.next_char(data_to_parse) {
+                Some((index, char)) => match char {
                     ' ' | '\t' => {
-                        self.state = ParserState::BeforeEquals;
-                        // *name_ends = index + self.position;
-                        self.set_name_end(index + self.position);
+                        self.finish_name_part(index);
+                        self.state = ParserState::AfterTableName(is_array);
                         return Ok(());
                     }
-                    '\n' | '\r' => {
-                        // Not valid - a name can't be multiline
+                    '.' => {
+                        // Dotted table name, ie [server.tls]
+                        self.finish_name_part(index);
+                        self.state = ParserState::BeforeTableName(is_array);
+                        return Ok(());
+                    }
+                    ']' => {
+                        self.finish_name_part(index);
+                        self.process_end_of_table_header(data_to_parse, is_array)?;
+                        return Ok(());
+                    }
+                    '[' | '#' | '\n' | '\r' => {
+                        // Not valid - a table header must be closed on the same line
                         return Err(Error::new(ErrorKind::InvalidName(self.line_number), None));
                     }
+                    char if Parser::is_bare_key_character(char) => {
+                        // No Op - part of the name
+                    }
                     _ => {
-                        println!("While reading name we got character: {} at {}", char, index);
-                        // No Op -
+                        return Err(Error::new(ErrorKind::InvalidBareKey(self.line_number), None));
                     }
                 },
                 None => {
-                    // File ended when reading the name - this is an error
+                    // File ended before the table header was closed
                     return Err(Error::new(ErrorKind::InvalidName(self.line_number), None));
                 }
             }
         }
     }
 
-    fn process_before_equals_state(
+    fn process_after_table_name_state(
         &mut self,
-        sequence: &mut std::iter::Enumerate<std::str::Chars>,
+        data_to_parse: &str,
+        is_array: bool,
     ) -> Result<(), Error> {
         loop {
-            match sequence.next() {
+            match self.next_char(data_to_parse) {
                 Some((_, char)) => match char {
-                    '=' => {
-                        self.state = ParserState::AfterEquals;
+                    ' ' | '\t' => {
+                        // No Op we are waiting for a . or a ]
+                    }
+                    '.' => {
+                        self.state = ParserState::BeforeTableName(is_array);
                         return Ok(());
                     }
-                    ' ' | '\t' => {
-                        // No Op we are waiting for a =
+                    ']' => {
+                        self.process_end_of_table_header(data_to_parse, is_array)?;
+                        return Ok(());
                     }
                     _ => {
-                        // This is invalid a name and a value must be seperated by a = and optionally whitespace
-                        // anything else is invalid
+                        // Parts of a table name must be seperated by a .
                         return Err(Error::new(ErrorKind::InvalidName(self.line_number), None));
                     }
                 },
                 None => {
-                    // File ended after a name but before a =
-                    return Err(Error::new(ErrorKind::MissingValue(self.line_number), None));
+                    // File ended before the table header was closed
+                    return Err(Error::new(ErrorKind::InvalidName(self.line_number), None));
                 }
             }
         }
     }
 
-    fn process_after_equals_state(
+    /// Called after seeing the first ] of a table header, an array of tables header must be closed with ]]
+    fn process_end_of_table_header(
         &mut self,
-        sequence: &mut std::iter::Enumerate<std::str::Chars>,
+        data_to_parse: &str,
+        is_array: bool,
     ) -> Result<(), Error> {
+        if is_array {
+            match self.next_char(data_to_parse) {
+                Some((_, ']')) => {}
+                _ => return Err(Error::new(ErrorKind::InvalidName(self.line_number), None)),
+            }
+        }
+        self.state = ParserState::FinishedTable(is_array);
+        Ok(())
+    }
+
+    /// Stores the part of the name that ends at the given index
+    fn finish_name_part(&mut self, name_end: usize) {
+        self.set_name_end(name_end);
+        let name_part = (self.name_start(), self.name_end(), false);
+        self.name_parts.push(name_part);
+        self.token_start = None;
+    }
+
+    /// Reads a quoted part of a name, ie "127.0.0.1" or 'quoted "value"', the opening quote has already been read
+    fn read_quoted_name(&mut self, data_to_parse: &str, quote: char) -> Result<(), Error> {
+        let is_basic_string = quote == '"';
+        let name_start = self.position;
+        self.token_start = Some(name_start);
+        if self.name_parts.is_empty() {
+            self.name_line = self.line_number;
+        }
         loop {
-            match sequence.next() {
+            match self.next_char(data_to_parse) {
                 Some((index, char)) => match char {
-                    ' ' | '\t' => {
-                        // No Op we are waiting for the start of a value
+                    char if char == quote => {
+                        if is_basic_string {
+                            // Check the escapes now so that the error is reported on the right line
+                            Parser::unescape_basic_string(
+                                &data_to_parse[name_start..index],
+                                name_start,
+                                self.line_number,
+                            )?;
+                        }
+                        self.name_parts.push((name_start, index, is_basic_string));
+                        self.token_start = None;
+                        return Ok(());
                     }
-                    '\n' => {
-                        return Err(Error::new(ErrorKind::MissingValue(self.line_number), None));
+                    '\\' if is_basic_string => {
+                        // Skip the escaped character so that \" doesn't end the name
+                        match self.next_char(data_to_parse) {
+                            Some((_, '\n')) | Some((_, '\r')) | None => {
+                                return Err(Error::new(
+                                    ErrorKind::InvalidName(self.line_number),
+                                    None,
+                                ));
+                            }
+                            Some(_) => {}
+                        }
                     }
-                    '\r' => {
-                        if let Some((_, '\n')) = sequence.next() {
-                            return Err(Error::new(
-                                ErrorKind::MissingValue(self.line_number),
-                                None,
-                            ));
-                        } else {
-                            return Err(Error::new(
+                    '\n' | '\r' => {
+                        // A quoted name must be on a single line
+                        return Err(Error::new(ErrorKind::InvalidName(self.line_number), None));
+                    }
+                    char if Parser::is_invalid_control_character(char) => {
+                        return Err(Error::new(
+                            ErrorKind::InvalidControlCharacter(self.line_number),
+                            None,
+                        ));
+                    }
+                    _ => {
+                        // No Op - part of the name
+                    }
+                },
+                None => {
+                    // File ended before the quoted name was closed
+                    return Err(Error::new(ErrorKind::InvalidName(self.line_number), None));
+                }
+            }
+        }
+    }
+
+    fn process_before_name_part_state(&mut self, data_to_parse: &str) -> Result<(), Error> {
+        loop {
+            match self.next_char(data_to_parse) {
+                Some((index, char)) => match char {
+                    ' ' | '\t' => {
+                        // No Op whitespace is allowed around the . in a dotted name
+                    }
+                    '.' | '=' | '#' | '\n' | '\r' => {
+                        // Each part of a dotted name must contain at least one character
+                        return Err(Error::new(ErrorKind::InvalidName(self.line_number), None));
+                    }
+                    '"' | '\'' => {
+                        self.read_quoted_name(data_to_parse, char)?;
+                        self.state = ParserState::BeforeEquals;
+                        return Ok(());
+                    }
+                    char if Parser::is_bare_key_character(char) => {
+                        self.state = ParserState::ReadingName;
+                        self.set_name_start(index);
+                        return Ok(());
+                    }
+                    _ => {
+                        return Err(Error::new(ErrorKind::InvalidBareKey(self.line_number), None));
+                    }
+                },
+                None => {
+                    // File ended when reading the name - this is an error
+                    return Err(Error::new(ErrorKind::InvalidName(self.line_number), None));
+                }
+            }
+        }
+    }
+
+    fn process_reading_name_state(&mut self, data_to_parse: &str) -> Result<(), Error> {
+        loop {
+            match self.next_char(data_to_parse) {
+                Some((index, char)) => match char {
+                    '=' => {
+                        self.state = ParserState::AfterEquals;
+                        self.finish_name_part(index);
+                        return Ok(());
+                    }
+                    ' ' | '\t' => {
+                        self.state = ParserState::BeforeEquals;
+                        self.finish_name_part(index);
+                        return Ok(());
+                    }
+                    '.' => {
+                        // Dotted name, ie physical.color = "orange"
+                        self.state = ParserState::BeforeNamePart;
+                        self.finish_name_part(index);
+                        return Ok(());
+                    }
+                    '\n' | '\r' => {
+                        // Not valid - a name can't be multiline
+                        return Err(Error::new(ErrorKind::InvalidName(self.line_number), None));
+                    }
+                    char if Parser::is_bare_key_character(char) => {
+                        // No Op - part of the name
+                    }
+                    _ => {
+                        // A bare name can only contain A-Z, a-z, 0-9, _ and -
+                        return Err(Error::new(ErrorKind::InvalidBareKey(self.line_number), None));
+                    }
+                },
+                None => {
+                    // File ended when reading the name - this is an error
+                    return Err(Error::new(ErrorKind::InvalidName(self.line_number), None));
+                }
+            }
+        }
+    }
+
+    fn process_before_equals_state(&mut self, data_to_parse: &str) -> Result<(), Error> {
+        loop {
+            match self.next_char(data_to_parse) {
+                Some((_, char)) => match char {
+                    '=' => {
+                        self.state = ParserState::AfterEquals;
+                        return Ok(());
+                    }
+                    '.' => {
+                        // Whitespace is allowed before the . in a dotted name
+                        self.state = ParserState::BeforeNamePart;
+                        return Ok(());
+                    }
+                    ' ' | '\t' => {
+                        // No Op we are waiting for a =
+                    }
+                    _ => {
+                        // This is invalid a name and a value must be seperated by a = and optionally whitespace
+                        // anything else is invalid
+                        return Err(Error::new(ErrorKind::InvalidName(self.line_number), None));
+                    }
+                },
+                None => {
+                    // File ended after a name but before a =
+                    return Err(Error::new(ErrorKind::MissingValue(self.line_number), None));
+                }
+            }
+        }
+    }
+
+    fn process_after_equals_state(&mut self, data_to_parse: &str) -> Result<(), Error> {
+        loop {
+            match self.peek_char(data_to_parse) {
+                Some((_, char)) => match char {
+                    ' ' | '\t' => {
+                        // No Op we are waiting for the start of a value
+                        self.next_char(data_to_parse);
+                    }
+                    '\n' => {
+                        return Err(Error::new(ErrorKind::MissingValue(self.line_number), None));
+                    }
+                    '\r' => {
+                        self.next_char(data_to_parse);
+                        if let Some((_, '\n')) = self.next_char(data_to_parse) {
+                            return Err(Error::new(
+                                ErrorKind::MissingValue(self.line_number),
+                                None,
+                            ));
+                        } else {
+                            return Err(Error::new(
                                 ErrorKind::InvalidEndOfLine(self.line_number),
                                 None,
                             ));
                         }
-                        // return Err(Error::new(ErrorKind::MissingValue(self.line_number), None));
                     }
                     '#' => {
                         // Invalid missing a value
                         return Err(Error::new(ErrorKind::MissingValue(self.line_number), None));
                     }
-                    '"' => {
-                        self.state = ParserState::ReadingString;
-                        self.set_value_start(index + self.position + 1);
+                    _ => {
+                        self.state = ParserState::StartOfValue;
                         return Ok(());
                     }
-                    // Booleans are always lower case...
-                    't' => {
-                        self.state = ParserState::ReadingTrue(index + self.position);
-                        self.set_value_start(index + self.position);
-                        return Ok(());
+                },
+                None => {
+                    // File ended after equals but before we saw a value
+                    return Err(Error::new(ErrorKind::MissingValue(self.line_number), None));
+                }
+            }
+        }
+    }
+
+    /// Reads the first character of a value to work out what type of value it is
+    fn process_start_of_value_state<'a>(
+        &mut self,
+        data_to_parse: &'a str,
+        open_values: &mut Vec<OpenValue<'a>>,
+    ) -> Result<(), Error> {
+        match self.next_char(data_to_parse) {
+            Some((index, char)) => match char {
+                '[' => {
+                    // Start of an array, the values inside it are read by the array states
+                    open_values.push(OpenValue::Array {
+                        values: Vec::new(),
+                        line_number: self.line_number,
+                        start: index,
+                        // Takes the name that is waiting for this value so that values inside the array can use their own names
+                        name: take(&mut self.name_parts),
+                    });
+                    self.state = ParserState::BeforeArrayValue;
+                    Ok(())
+                }
+                '{' => {
+                    // Start of an inline table, the pairs inside it are read by the inline table states
+                    open_values.push(OpenValue::InlineTable {
+                        table: Box::new(DocumentBuilder::new()),
+                        line_number: self.line_number,
+                        name: take(&mut self.name_parts),
+                    });
+                    self.state = ParserState::BeforeInlineTableName;
+                    Ok(())
+                }
+                '"' | '\'' => {
+                    let is_basic_string = char == '"';
+                    if self.start_multi_line_string(data_to_parse, char) {
+                        self.state = ParserState::ReadingMultiLineString(is_basic_string);
+                    } else if is_basic_string {
+                        self.state = ParserState::ReadingString;
+                        self.set_value_start(index + 1);
+                    } else {
+                        self.state = ParserState::ReadingLiteralString;
+                        self.set_value_start(index + 1);
                     }
-                    'f' => {
-                        self.state = ParserState::ReadingFalse(index + self.position);
-                        self.set_value_start(index + self.position);
-                        return Ok(());
+                    Ok(())
+                }
+                // Booleans are always lower case...
+                't' => {
+                    self.state = ParserState::ReadingTrue(index);
+                    self.set_value_start(index);
+                    Ok(())
+                }
+                'f' => {
+                    self.state = ParserState::ReadingFalse(index);
+                    self.set_value_start(index);
+                    Ok(())
+                }
+                'i' | 'n' => {
+                    // Special float values inf and nan
+                    self.state = ParserState::ReadingSpecialFloat(index);
+                    Ok(())
+                }
+                char if char.is_ascii_digit() && Parser::is_date_time(&data_to_parse[index..]) => {
+                    self.state = ParserState::ReadingDateTime(index);
+                    self.set_value_start(index);
+                    Ok(())
+                }
+                '+' | '-' => {
+                    // Only decimal integers, floats, inf and nan can be signed
+                    match self.peek_char(data_to_parse) {
+                        Some((_, 'i')) | Some((_, 'n')) => {
+                            self.state = ParserState::ReadingSpecialFloat(index);
+                            Ok(())
+                        }
+                        Some((digit_index, char)) if char.is_ascii_digit() => {
+                            self.next_char(data_to_parse);
+                            self.process_first_digit(data_to_parse, index, digit_index, char)
+                        }
+                        Some((_, '+')) | Some((_, '-')) => {
+                            Err(Error::new(ErrorKind::InvalidSign(self.line_number), None))
+                        }
+                        Some((_, '_')) => {
+                            Err(Error::new(ErrorKind::InvalidUnderscore(self.line_number), None))
+                        }
+                        Some((_, '.')) => {
+                            Err(Error::new(ErrorKind::InvalidFloat(self.line_number), None))
+                        }
+                        _ => {
+                            Err(Error::new(ErrorKind::InvalidValue(self.line_number), None))
+                        }
                     }
-                    '0' => {
-                        // This could be a 0
-                        // or a 0.0423 float
-                        match sequence.next() {
-                            Some((after_zero_index, char)) => {
-                                match char {
-                                    ' ' | '\t' => {
-                                        // Basic integer 0
-                                        self.state = ParserState::FinishedInteger(false, false);
-                                        self.set_value_start(index + self.position);
-                                        self.set_value_end(after_zero_index);
-                                        return Ok(());
-                                    },
-                                    '.' => {
-                                        self.state = ParserState::ReadingFloat(false);
-                                        self.set_value_start(index + self.position);
-                                        return Ok(());
-                                    },
-                                    'e' | 'E' => {
-                                        // TODO: 0e2 is valid?
-                                        self.state = ParserState::ReadingFloat(true);
-                                        self.set_value_start(index + self.position);
-                                        return Ok(());
-                                    },
-                                    '\n' => {
-                                        // Basic integer zero followed by a new line
-                                        self.state = ParserState::FinishedInteger(false, true);
-                                        self.set_value_start(index + self.position);
-                                        self.set_value_end(after_zero_index);
-                                        return Ok(());
-                                    }
-                                    '\r' => {
-                                        if let Some((_, '\n')) = sequence.next() {
-                                            // Basic integer zero followed by a new line
-                                            self.state = ParserState::FinishedInteger(false, true);
-                                            self.set_value_start(index + self.position);
-                                            self.set_value_end(after_zero_index);
-                                            return Ok(());
-                                        } else {
-                                            return Err(Error::new(
-                                                ErrorKind::InvalidEndOfLine(self.line_number),
-                                                None,
-                                            ));
-                                        }
-                                    }
-                                    'x' => {
-                                        // Hex int
-                                        self.state = ParserState::ReadingHexInteger;
-                                        self.set_value_start(after_zero_index + self.position + 1);
-                                        return Ok(());
-                                    }
-                                    'b' => {
-                                        // binary int
-                                        self.state = ParserState::ReadingBinaryInteger;
-                                        self.set_value_start(after_zero_index + self.position + 1);
-                                        return Ok(());
-                                    }
-                                    'o' => {
-                                        // octal int
-                                        self.state = ParserState::ReadingOctalInteger;
-                                        self.set_value_start(after_zero_index + self.position + 1);
-                                        return Ok(());
-                                    }
-                                    char if char.is_ascii_digit() == true => {
-                                        // Explicitly not allowed
-                                        return Err(Error::new(
-                                            ErrorKind::InvalidValue(self.line_number),
-                                            None,
-                                        ));
-                                    }
-                                    _ => {
-                                        // TODO: There may be valid combinations left
-                                        println!("Generic catch all hit after seeing a 0, may not be correct");
-                                        return Err(Error::new(
-                                            ErrorKind::InvalidValue(self.line_number),
-                                            None,
-                                        ));
-                                    }
-                                }
-                            }
-                            None => {
-                                // File ended on a zero so we read a zero integer
-                                self.state = ParserState::FinishedInteger(true, false);
-                                self.set_value_start(index + self.position);
-                                // TODO: Careful here as this could be unsafe???
-                                self.set_value_end(index + self.position + 1);
-                                return Ok(());
+                }
+                '_' => {
+                    // An underscore must be between two digits
+                    Err(Error::new(ErrorKind::InvalidUnderscore(self.line_number), None))
+                }
+                '.' => {
+                    // A float must have a digit before the decimal point, ie .5 is invalid
+                    Err(Error::new(ErrorKind::InvalidFloat(self.line_number), None))
+                }
+                char if char.is_ascii_digit() => {
+                    // We could be reading a float or an integer
+                    self.process_first_digit(data_to_parse, index, index, char)
+                }
+                _ => {
+                    // This should be an error since we have hit a value we dont recognize
+                    Err(Error::new(
+                        ErrorKind::UnknownValueType(self.line_number),
+                        None,
+                    ))
+                }
+            },
+            None => {
+                // File ended before we saw a value
+                Err(Error::new(ErrorKind::MissingValue(self.line_number), None))
+            }
+        }
+    }
+
+    /// Expects the next value in an array, the end of the array, whitespace, new lines or comments
+    fn process_before_array_value_state<'a>(
+        &mut self,
+        data_to_parse: &'a str,
+        open_values: &mut Vec<OpenValue<'a>>,
+    ) -> Result<Option<TomlPair<'a>>, Error> {
+        loop {
+            match self.peek_char(data_to_parse) {
+                Some((_, char)) => match char {
+                    ' ' | '\t' | '\n' | '\r' | '#' => {
+                        self.process_array_whitespace(data_to_parse)?;
+                    }
+                    ']' => {
+                        // Empty array or an array ending with a trailing comma
+                        self.next_char(data_to_parse);
+                        return self.finish_open_value(data_to_parse, open_values);
+                    }
+                    ',' => {
+                        // A comma must follow a value
+                        return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
+                    }
+                    _ => {
+                        self.state = ParserState::StartOfValue;
+                        return Ok(None);
+                    }
+                },
+                None => {
+                    return Err(self.unterminated_array_error(open_values));
+                }
+            }
+        }
+    }
+
+    /// Expects a comma or the end of the array, whitespace, new lines and comments are allowed before either
+    fn process_after_array_value_state<'a>(
+        &mut self,
+        data_to_parse: &'a str,
+        open_values: &mut Vec<OpenValue<'a>>,
+    ) -> Result<Option<TomlPair<'a>>, Error> {
+        loop {
+            match self.peek_char(data_to_parse) {
+                Some((_, char)) => match char {
+                    ' ' | '\t' | '\n' | '\r' | '#' => {
+                        self.process_array_whitespace(data_to_parse)?;
+                    }
+                    ',' => {
+                        self.next_char(data_to_parse);
+                        self.state = ParserState::BeforeArrayValue;
+                        return Ok(None);
+                    }
+                    ']' => {
+                        self.next_char(data_to_parse);
+                        return self.finish_open_value(data_to_parse, open_values);
+                    }
+                    _ => {
+                        // Values in an array must be seperated by a comma
+                        return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
+                    }
+                },
+                None => {
+                    return Err(self.unterminated_array_error(open_values));
+                }
+            }
+        }
+    }
+
+    /// Whitespace, new lines and comments are allowed anywhere between the values of an array
+    fn process_array_whitespace(&mut self, data_to_parse: &str) -> Result<(), Error> {
+        match self.next_char(data_to_parse) {
+            Some((_, '\n')) => self.line_number += 1,
+            Some((_, '\r')) => self.process_carriage_return(data_to_parse)?,
+            Some((_, '#')) => self.read_comment(data_to_parse)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The error for a file that ends inside an array, it covers the whole array
+    fn unterminated_array_error(&self, open_values: &[OpenValue]) -> Error {
+        let error = Error::new(
+            ErrorKind::UnterminatedArray(Parser::open_value_line(open_values)),
+            None,
+        );
+        match open_values.last() {
+            Some(OpenValue::Array { start, .. }) => error.with_span(*start..self.position),
+            _ => error,
+        }
+    }
+
+    /// Returns the line that the innermost array or inline table started on
+    fn open_value_line(open_values: &[OpenValue]) -> usize {
+        match open_values.last() {
+            Some(OpenValue::Array { line_number, .. }) => *line_number,
+            Some(OpenValue::InlineTable { line_number, .. }) => *line_number,
+            None => 0,
+        }
+    }
+
+    /// Expects the next name in an inline table or the end of an empty inline table
+    fn process_before_inline_table_name_state<'a>(
+        &mut self,
+        data_to_parse: &'a str,
+        open_values: &mut Vec<OpenValue<'a>>,
+    ) -> Result<Option<TomlPair<'a>>, Error> {
+        loop {
+            match self.next_char(data_to_parse) {
+                Some((index, char)) => match char {
+                    ' ' | '\t' => {
+                        // No Op we are waiting for a name
+                    }
+                    '}' => {
+                        // Only an empty inline table can end here, a trailing comma is not allowed
+                        if let Some(OpenValue::InlineTable { table, .. }) = open_values.last() {
+                            if table.is_empty() {
+                                return self.finish_open_value(data_to_parse, open_values);
                             }
                         }
+                        return Err(Error::new(
+                            ErrorKind::InvalidInlineTable(Parser::open_value_line(open_values)),
+                            None,
+                        ));
                     }
-                    '-' => {
-                        self.state = ParserState::ReadingNegativeInteger;
-                        match sequence.next() {
-                            Some((after_negative_sign, char)) => {
-                                match char {
-                                    '0' => {
-                                        // Special case -0
-                                        // The only allowed values after a -0 are whitespace, end of line or end of file
-                                        match sequence.next() {
-                                            Some((after_zero_index, char)) => {
-                                                match char {
-                                                    ' ' | '\t' => {
-                                                        // Integer finished
-                                                        self.state = ParserState::FinishedInteger(false, false);
-                                                        self.set_value_start(after_negative_sign);
-                                                        self.set_value_end(after_zero_index);
-                                                        self.position += after_zero_index + 1;
-                                                        return Ok(());
-                                                    },
-                                                    '\n' => {
-                                                        // Integer finished
-                                                        self.state = ParserState::FinishedInteger(false, true);
-                                                        self.set_value_start(after_negative_sign);
-                                                        self.set_value_end(after_zero_index);
-                                                        self.position += after_zero_index + 1;
-                                                        self.line_number += 1;
-                                                        return Ok(());
-                                                    },
-                                                    '\r' => {
-                                                        if let Some((_, '\n')) = sequence.next() {
-                                                            // Basic integer zero followed by a new line
-                                                            self.state = ParserState::FinishedInteger(false, true);
-                                                            self.set_value_start(after_negative_sign);
-                                                            self.set_value_end(after_zero_index);
-                                                            self.position += after_zero_index + 2;
-                                                            return Ok(());
-                                                        } else {
-                                                            return Err(Error::new(
-                                                                ErrorKind::InvalidEndOfLine(self.line_number),
-                                                                None,
-                                                            ));
-                                                        }
-                                                    },
-                                                    '#' => {
-                                                        unimplemented!("Comments not done");
-                                                    },
-                                                    _ => {
-                                                        return Err(Error::new(
-                                                                ErrorKind::InvalidEndOfLine(self.line_number),
-                                                                None,
-                                                            ));
-                                                    },
-                                                }
-                                            },
-                                            None => {
-                                                // Valid - File ended on a -0
-                                                self.set_value_start(after_negative_sign);
-                                                self.set_value_end(after_negative_sign + 1);
-                                                self.state = ParserState::FinishedInteger(true, false);
-                                                return Ok(());
-                                            },
-                                        }
-                                    },
-                                    char if char.is_ascii_digit() == true => {
-                                        self.state = ParserState::ReadingNegativeInteger;
-                                        self.set_value_start(self.position + after_negative_sign);
-                                        return Ok(());
-                                    },
-                                    _ => {
-                                        // Invalid
-                                        return Err(Error::new(ErrorKind::InvalidValue(self.position), None));
-                                    }
-                                }
-                            },
-                            None => {
-                                // Invalid
-                                return Err(Error::new(ErrorKind::InvalidValue(self.position), None));
-                            }
-                        }                        
+                    '\n' | '\r' | '#' | ',' => {
+                        // Inline tables must be on a single line
+                        return Err(Error::new(
+                            ErrorKind::InvalidInlineTable(Parser::open_value_line(open_values)),
+                            None,
+                        ));
                     }
-                    '+' => {
-                        // -0 and +0 are valid and identical to an unprefixed zero
-                        // + can only be a base 10 integer
+                    '.' | '=' => {
+                        // A name can't start with a . or be empty
+                        return Err(Error::new(ErrorKind::InvalidName(self.line_number), None));
                     }
-                    '_' => {
-                        // Invalid integer seperator
-                        unimplemented!("Invalid integer seperator, ie value started with one, clever errors not finished")
+                    '"' | '\'' => {
+                        self.read_quoted_name(data_to_parse, char)?;
+                        self.state = ParserState::BeforeEquals;
+                        return Ok(None);
                     }
-                    char if char.is_ascii_digit() == true => {
-                        // We could be reading a float or a decimal integer
-                        self.state = ParserState::ReadingInteger;
-                        self.set_value_start(index + self.position);
-                        return Ok(());
+                    char if Parser::is_bare_key_character(char) => {
+                        self.state = ParserState::ReadingName;
+                        self.set_name_start(index);
+                        return Ok(None);
                     }
-                    // TODO: Support for multiline strings etc
                     _ => {
-                        // This should be an error since we have hit a value we dont recognize
+                        return Err(Error::new(ErrorKind::InvalidBareKey(self.line_number), None));
+                    }
+                },
+                None => {
+                    // File ended before the inline table was closed
+                    return Err(Error::new(
+                        ErrorKind::InvalidInlineTable(Parser::open_value_line(open_values)),
+                        None,
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Expects a comma or the end of the inline table
+    fn process_after_inline_table_value_state<'a>(
+        &mut self,
+        data_to_parse: &'a str,
+        open_values: &mut Vec<OpenValue<'a>>,
+    ) -> Result<Option<TomlPair<'a>>, Error> {
+        loop {
+            match self.next_char(data_to_parse) {
+                Some((_, char)) => match char {
+                    ' ' | '\t' => {
+                        // No Op we are waiting for a comma or the end of the table
+                    }
+                    ',' => {
+                        self.state = ParserState::BeforeInlineTableName;
+                        return Ok(None);
+                    }
+                    '}' => {
+                        return self.finish_open_value(data_to_parse, open_values);
+                    }
+                    '\n' | '\r' | '#' => {
+                        // Inline tables must be on a single line
                         return Err(Error::new(
-                            ErrorKind::UnknownValueType(self.line_number),
+                            ErrorKind::InvalidInlineTable(Parser::open_value_line(open_values)),
                             None,
                         ));
                     }
+                    _ => {
+                        // Pairs in an inline table must be seperated by a comma
+                        return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
+                    }
                 },
                 None => {
-                    // File ended after equals but before we saw a value
-                    return Err(Error::new(ErrorKind::MissingValue(self.line_number), None));
+                    // File ended before the inline table was closed
+                    return Err(Error::new(
+                        ErrorKind::InvalidInlineTable(Parser::open_value_line(open_values)),
+                        None,
+                    ));
                 }
             }
         }
     }
 
-    fn process_negative_integer<'a>(&mut self, sequence: &mut std::iter::Enumerate<std::str::Chars>,
-        data_to_parse: &'a str,
+    /// Called after the first digit of a decimal number, the value starts at the sign if there is one
+    fn process_first_digit(
+        &mut self,
+        data_to_parse: &str,
+        value_start: usize,
+        digit_index: usize,
+        digit: char,
     ) -> Result<(), Error> {
-        match sequence.next() {
+        let is_signed = value_start != digit_index;
+        if digit != '0' {
+            self.state = ParserState::ReadingInteger;
+            self.set_value_start(value_start);
+            return Ok(());
+        }
+        // A zero can only be followed by a prefix, a decimal point or an exponent
+        match self.peek_char(data_to_parse) {
+            Some((after_zero_index, char)) => match char {
+                char if Parser::is_value_terminator(char) => {
+                    // Basic integer 0, -0 and +0 are identical to an unprefixed zero
+                    self.state = ParserState::FinishedInteger;
+                    self.set_value_start(value_start);
+                    self.set_value_end(after_zero_index);
+                    Ok(())
+                }
+                '.' => {
+                    self.set_value_start(value_start);
+                    self.start_fraction(data_to_parse)
+                }
+                'e' | 'E' => {
+                    self.set_value_start(value_start);
+                    self.start_exponent(data_to_parse)
+                }
+                'x' | 'o' | 'b' if is_signed => {
+                    Err(Error::new(ErrorKind::InvalidSign(self.line_number), None))
+                }
+                'x' | 'o' | 'b' => {
+                    self.next_char(data_to_parse);
+                    self.state = match char {
+                        'x' => ParserState::ReadingHexInteger,
+                        'o' => ParserState::ReadingOctalInteger,
+                        _ => ParserState::ReadingBinaryInteger,
+                    };
+                    self.set_value_start(after_zero_index + 1);
+                    Ok(())
+                }
+                'X' | 'O' | 'B' => {
+                    Err(Error::new(ErrorKind::InvalidPrefix(self.line_number), None))
+                }
+                char if char.is_ascii_digit() || char == '_' => {
+                    Err(Error::new(ErrorKind::LeadingZero(self.line_number), None))
+                }
+                _ => {
+                    Err(Error::new(ErrorKind::InvalidValue(self.line_number), None))
+                }
+            },
+            None => {
+                // File ended on a zero so we read a zero integer
+                self.state = ParserState::FinishedInteger;
+                self.set_value_start(value_start);
+                self.set_value_end(digit_index + 1);
+                Ok(())
+            }
+        }
+    }
+
+    /// Reads the . of a float, it must be followed by a digit
+    fn start_fraction(&mut self, data_to_parse: &str) -> Result<(), Error> {
+        self.next_char(data_to_parse);
+        match self.peek_char(data_to_parse) {
+            Some((_, char)) if char.is_ascii_digit() => {
+                self.state = ParserState::ReadingFloat(false);
+                Ok(())
+            }
+            _ => {
+                // Error - 1. and 1.e5 are invalid
+                Err(Error::new(ErrorKind::InvalidFloat(self.line_number), None))
+            }
+        }
+    }
+
+    /// Reads the e of a float and the optional sign of the exponent, it must be followed by a digit
+    fn start_exponent(&mut self, data_to_parse: &str) -> Result<(), Error> {
+        self.next_char(data_to_parse);
+        if let Some((_, '+')) | Some((_, '-')) = self.peek_char(data_to_parse) {
+            self.next_char(data_to_parse);
+        }
+        match self.peek_char(data_to_parse) {
+            // The exponent can have leading zeros, 21.5436e06 is valid
+            Some((_, char)) if char.is_ascii_digit() => {
+                self.state = ParserState::ReadingFloat(true);
+                Ok(())
+            }
+            _ => {
+                // Error - Exponential part of float was empty, including a file ending with a line like
+                // fred = 23.457e
+                Err(Error::new(ErrorKind::InvalidFloat(self.line_number), None))
+            }
+        }
+    }
+
+    /// An underscore in a number must be between two digits, ie 1_000
+    fn process_number_underscore(
+        &mut self,
+        data_to_parse: &str,
+        index: usize,
+        radix: u32,
+    ) -> Result<(), Error> {
+        let is_after_digit = (data_to_parse.as_bytes()[index - 1] as char).is_digit(radix);
+        self.next_char(data_to_parse);
+        match self.next_char(data_to_parse) {
+            Some((_, char)) if is_after_digit && char.is_digit(radix) => {
+                Ok(())
+            }
+            _ => {
+                // Invalid, including a value that ended with a _
+                Err(Error::new(ErrorKind::InvalidUnderscore(self.line_number), None))
+            }
+        }
+    }
+
+    /// A hex, octal or binary integer must have at least one digit after the prefix, ie 0x is invalid
+    fn finish_prefixed_integer(
+        &mut self,
+        data_to_parse: &str,
+        value_end: usize,
+        radix: u32,
+        state: ParserState,
+    ) -> Result<(), Error> {
+        if !(data_to_parse.as_bytes()[value_end - 1] as char).is_digit(radix) {
+            return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
+        }
+        self.set_value_end(value_end);
+        self.state = state;
+        Ok(())
+    }
+
+    fn process_read_integer_state(&mut self, data_to_parse: &str) -> Result<(), Error> {
+        match self.peek_char(data_to_parse) {
             Some((index, char)) => match char {
-                char if char.is_ascii_digit() == true => {
-                    // Negative Integer digit
-                    return Ok(());
-                },
+                char if char.is_ascii_digit() => {
+                    // No Op
+                    self.next_char(data_to_parse);
+                    Ok(())
+                }
+                char if Parser::is_value_terminator(char) => {
+                    // Whitespace, a comment or a new line means the integer ended.
+                    // The character ending the integer is processed by the AfterValue state
+                    self.state = ParserState::FinishedInteger;
+                    self.set_value_end(index);
+                    Ok(())
+                }
                 '.' => {
-                    // Negative float
-                    unimplemented!("Negative floats not supported");
-                },
+                    self.start_fraction(data_to_parse)
+                }
+                'e' | 'E' => {
+                    self.start_exponent(data_to_parse)
+                }
                 '_' => {
-                    // This is valid as long as its folowed by a digit
-                    return Ok(());
+                    self.process_number_underscore(data_to_parse, index, 10)
                 }
-                '\r' => {
-                    if let Some((_, '\n')) = sequence.next() {
-                        self.state = ParserState::FinishedNegativeInteger(false, true);
-                        self.line_number += 1;
-                        self.set_value_end(index + self.position);
-                        self.position += index;
-                        return Ok(());
-                    } else {
-                        return Err(Error::new(ErrorKind::InvalidEndOfLine(self.line_number), None));
-                    }
-                },
-                '\n' => {
-                        self.state = ParserState::FinishedNegativeInteger(false, true);
-                        self.line_number += 1;
-                        self.set_value_end(index + self.position);
-                        self.position += index;
-                        return Ok(());
-                },
-                ' ' | '\t' => {
-                    self.state = ParserState::FinishedNegativeInteger(false, false);
-                        self.set_value_end(index + self.position);
-                        self.position += index;
-                        return Ok(());
+                _ => {
+                    // Error - invalid character in integer
+                    Err(Error::new(ErrorKind::InvalidValue(self.line_number), None))
                 }
-                _ => return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None)),
             },
             None => {
-                self.state = ParserState::FinishedNegativeInteger(true, false);
+                // File ended while reading an integer, this is valid the end of the file denotes the end of the integer
                 self.set_value_end(data_to_parse.len());
-                // TODO: self.position is invalid at this point as it wasn't incremented by the length of the last character
-                return Ok(());
+                self.state = ParserState::FinishedInteger;
+                Ok(())
             }
         }
     }
 
-    fn process_read_integer_state<'a>(
+    fn process_read_float_state(
         &mut self,
-        sequence: &mut std::iter::Enumerate<std::str::Chars>,
-        data_to_parse: &'a str,
+        data_to_parse: &str,
+        after_exponent: bool,
     ) -> Result<(), Error> {
-        // TODO: Deal with zero
-        match sequence.next() {
+        match self.peek_char(data_to_parse) {
             Some((index, char)) => match char {
-                char if char.is_ascii_digit() == true => {
+                char if char.is_ascii_digit() => {
                     // No Op
-                    return Ok(());
+                    self.next_char(data_to_parse);
+                    Ok(())
+                }
+                char if Parser::is_value_terminator(char) => {
+                    // Whitespace, a comment or a new line means the float ended.
+                    // The character ending the float is processed by the AfterValue state
+                    self.set_value_end(index);
+                    self.state = ParserState::FinishedFloat;
+                    Ok(())
                 }
-                ' ' | '\t' => {
-                    // Whitespace means the integer ended.
-                    // This means we are still looking for a new line but the integer is done
-                    self.state = ParserState::FinishedInteger(false, false);
-                    self.set_value_end(index + self.position);
-                    self.position += index;
-                    // Moving to AfterValue state
-                    // self.state = ParserState::AfterValue;
-                    // return Ok(self.build_integer_pair(data_to_parse)?);
-                    return Ok(());
-                },
                 '.' => {
-                    // unimplemented!("No support for floats");
-                    self.state = ParserState::ReadingFloat(false);
-                    // let float_pair = self.process_read_float_state(sequence, data_to_parse)?;
-                    return Ok(());
-                    //break;
-                },
+                    // This is always an error since a period will always come before an exponential
+                    // 1.34e5 is valid
+                    // 1e02.45 is not
+                    Err(Error::new(ErrorKind::InvalidFloat(self.line_number), None))
+                }
                 'e' | 'E' => {
-                    self.state = ParserState::ReadingFloat(true);
-                    // let float_pair = self.process_read_float_state(sequence, data_to_parse)?;
-                    return Ok(());
-                },
+                    // Check if we have already seen a e
+                    if after_exponent {
+                        return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
+                    }
+                    self.start_exponent(data_to_parse)
+                }
                 '_' => {
-                    // not a no op, read the next char to ensure it is a digit
-                    match sequence.next() {
-                        Some((_, char)) => {
-                            match char {
-                                char if char.is_ascii_digit() == true => {
-                                    return Ok(());
-                                }
-                                _ => {
-                                    // Invalid
-                                    return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
-                                }
-                            }
-                        },
-                        None => {
-                            // Value ended with a _ which is invalid
-                            return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
+                    self.process_number_underscore(data_to_parse, index, 10)
+                }
+                _ => {
+                    // Error - invalid character in float
+                    Err(Error::new(ErrorKind::InvalidValue(self.line_number), None))
+                }
+            },
+            None => {
+                // File ended while reading a float, this is valid the end of the file denotes the end of the float
+                self.set_value_end(data_to_parse.len());
+                self.state = ParserState::FinishedFloat;
+                Ok(())
+            }
+        }
+    }
+
+    fn process_read_string_state(&mut self, data_to_parse: &str) -> Result<(), Error> {
+        match self.next_char(data_to_parse) {
+            Some((index, char)) => match char {
+                '"' => {
+                    // End of the string
+                    // We are now scanning for the end of line
+                    trace!("Found end of string at {}", index);
+                    self.set_value_end(index);
+                    self.state = ParserState::FinishedString;
+                    Ok(())
+                }
+                '\n' | '\r' => {
+                    // End of line without ending the string - only multi-line strings can contain a newline
+                    Err(Error::new(ErrorKind::InvalidValue(self.line_number), None))
+                }
+                '\\' => {
+                    // Skip the escaped character so that \" doesn't end the string
+                    // The escapes are replaced when the value is built since that needs a copy of the string
+                    match self.next_char(data_to_parse) {
+                        Some((_, '\n')) | Some((_, '\r')) | None => {
+                            Err(Error::new(ErrorKind::InvalidValue(self.line_number), None))
+                        }
+                        Some(_) => {
+                            Ok(())
                         }
                     }
                 }
-                '\n' => {
-                    // End of integer
-                    self.state = ParserState::FinishedInteger(false, true);
-                    // Our state transition when finishing a value is based on the character that was read during the transition to finish
-                    // (state, previous_character)
-                    self.line_number += 1;
-                    self.set_value_end(index + self.position);
-                    self.position += index;
-                    // return Ok(self.build_integer_pair(data_to_parse)?);
-                    return Ok(());
+                char if Parser::is_invalid_control_character(char) => {
+                    // Control characters other than tab must be escaped
+                    Err(Error::new(
+                        ErrorKind::InvalidControlCharacter(self.line_number),
+                        None,
+                    ))
+                }
+                _ => {
+                    // A character - there are some characters that will be illegal
+                    Ok(())
                 }
-                '\r' => {
-                    // Next character must be \n and then return pair
-                    if let Some((_, '\n')) = sequence.next() {
-                        self.state = ParserState::FinishedInteger(false, true);
-                        self.line_number += 1;
-                        self.set_value_end(index + self.position);
-                        self.position += index;
-                        // We set the previous token so that when we move from finished state we move to normal rather than after value
-                        // return Ok(self.build_integer_pair(data_to_parse)?);
+            },
+            None => {
+                // We have reached the end of the file
+                // This is invalid a string is only valid if it is ended with a "
+                Err(Error::new(ErrorKind::InvalidValue(self.line_number), None))
+            }
+        }
+    }
+
+    fn process_read_literal_string_state(&mut self, data_to_parse: &str) -> Result<(), Error> {
+        loop {
+            match self.next_char(data_to_parse) {
+                Some((index, char)) => match char {
+                    '\'' => {
+                        // End of the string, a literal string can't contain a '
+                        self.set_value_end(index);
+                        self.state = ParserState::FinishedLiteralString;
                         return Ok(());
-                        // Integer is done and we are moving to NewLine state
-                    } else {
-                        // Error - invalid integer because of invalid end of line
+                    }
+                    '\n' | '\r' => {
+                        // A literal string must be on a single line
+                        return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
+                    }
+                    char if Parser::is_invalid_control_character(char) => {
+                        // Control characters other than tab can't appear in a literal string since they can't be escaped
                         return Err(Error::new(
-                            ErrorKind::InvalidEndOfLine(self.line_number),
+                            ErrorKind::InvalidControlCharacter(self.line_number),
                             None,
                         ));
                     }
-                }
-                _ => {
-                    // Error - invalid character in integer
+                    _ => {
+                        // No Op - the content is taken verbatim
+                    }
+                },
+                None => {
+                    // This is invalid a literal string is only valid if it is ended with a '
                     return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
                 }
-            },
-            None => {
-                // File ended while reading an integer, this is valid the end of the file denotes the end of the integer
-                self.set_value_end(data_to_parse.len());
-                self.state = ParserState::FinishedInteger(true, false);
-                // TODO: This could lead to problems like reading the next character of the sequence no longer returns a null
-                // return Ok(self.build_integer_pair(data_to_parse)?);
-                return Ok(());
             }
         }
     }
 
-    fn process_read_float_state<'a>(
+    /// Called after the first quote of a string, returns true if this is the start of a multi-line string
+    /// A newline straight after the opening delimiter is not part of the string
+    fn start_multi_line_string(&mut self, data_to_parse: &str, quote: char) -> bool {
+        let delimiter = if quote == '"' { "\"\"" } else { "''" };
+        if !data_to_parse[self.position..].starts_with(delimiter) {
+            return false;
+        }
+        self.position += delimiter.len();
+        let rest = &data_to_parse[self.position..];
+        if rest.starts_with('\n') {
+            self.position += 1;
+            self.line_number += 1;
+        } else if rest.starts_with("\r\n") {
+            self.position += 2;
+            self.line_number += 1;
+        }
+        self.set_value_start(self.position);
+        true
+    }
+
+    fn process_read_multi_line_string_state(
         &mut self,
-        sequence: &mut std::iter::Enumerate<std::str::Chars>,
-        data_to_parse: &'a str,
-        after_exponent: bool,
+        data_to_parse: &str,
+        is_basic_string: bool,
     ) -> Result<(), Error> {
-        match sequence.next() {
-            Some((index, char)) => match char {
-                char if char.is_ascii_digit() == true => {
-                    // No Op
-                    return Ok(());
-                }
-                ' ' | '\t' => {
-                    // Whitespace means the integer ended.
-                    // This means we are still looking for a new line but the integer is done
-                    // self.state = ParserState::AfterValue;
-                    self.set_value_end(index + self.position);
-                    self.position += index;
-                    // Moving to AfterValue state
-                    self.state = ParserState::FinishedFloat(false, false);
-                    return Ok(());
-                }
-                '.' => {
-                    // This is always an error since a period will always come before an exponential
-                    // 1.34e5 is valid
-                    // 1e02.45 is not
-                    return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
-                },
-                'e' | 'E' => {
-                    // Check if we have already seen a e
-                    if after_exponent {
-                        return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
-                    }
-                    // Change state so that we know we are reading the exponent now
-                    self.state = ParserState::ReadingFloat(true);
-                    // Next character must be a digit for this to be valid, but that digit can be a zero
-                    // 23.456e0 is valid
-                    // 21.5436e06 is valid
-                    match sequence.next() {
-                        Some((index, char)) => {
-                            match char {
-                                char if char.is_ascii_digit() == true => {
-                                    return Ok(());
-                                }
-                                _ => {
-                                    // Error - Exponential part of float was empty
-                                    return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
-                                }
+        let quote = if is_basic_string { '"' } else { '\'' };
+        loop {
+            match self.next_char(data_to_parse) {
+                Some((index, char)) => match char {
+                    char if char == quote => {
+                        // Up to two quotes are allowed just before the closing delimiter
+                        let quotes = data_to_parse[index..]
+                            .chars()
+                            .take_while(|char| *char == quote)
+                            .count();
+                        self.position = index + quotes;
+                        if quotes >= 3 {
+                            if quotes > 5 {
+                                return Err(Error::new(
+                                    ErrorKind::InvalidValue(self.line_number),
+                                    None,
+                                ));
                             }
-                            
-                        }
-                        None => {
-                            // Error - File ended with line like
-                            //fred = 23.457e
-                            return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
+                            self.set_value_end(index + quotes - 3);
+                            if is_basic_string {
+                                self.state = ParserState::FinishedString;
+                            } else {
+                                self.state = ParserState::FinishedLiteralString;
+                            }
+                            return Ok(());
                         }
                     }
-                }
-                '\n' => {
-                    // End of integer
-                    self.state = ParserState::FinishedFloat(false, true);
-                    // Our state transition when finishing a value is based on the character that was read during the transition to finish
-                    // (state, previous_character)
-                    self.line_number += 1;
-                    self.set_value_end(index + self.position);
-                    self.position += index;
-                    return Ok(());
-                }
-                '\r' => {
-                    // Next character must be \n and then return pair
-                    if let Some((index, '\n')) = sequence.next() {
-                        self.state = ParserState::FinishedFloat(false, true);
+                    '\n' => {
                         self.line_number += 1;
-                        self.set_value_end(index + self.position - 1);
-                        self.position += index;
-                        // We set the previous token so that when we move from finished state we move to normal rather than after value
-                        return Ok(());
-                        // Integer is done and we are moving to NewLine state
-                    } else {
-                        // Error - invalid integer because of invalid end of line
+                    }
+                    '\r' => {
+                        self.process_carriage_return(data_to_parse)?;
+                    }
+                    '\\' if is_basic_string => {
+                        // Skip the escaped character so that \" doesn't end the string, the escapes are replaced when the value is built
+                        match self.next_char(data_to_parse) {
+                            Some((_, '\n')) => {
+                                self.line_number += 1;
+                            }
+                            Some((_, '\r')) => {
+                                self.process_carriage_return(data_to_parse)?;
+                            }
+                            Some(_) => {}
+                            None => {
+                                return Err(Error::new(
+                                    ErrorKind::InvalidValue(self.line_number),
+                                    None,
+                                ));
+                            }
+                        }
+                    }
+                    char if Parser::is_invalid_control_character(char) => {
                         return Err(Error::new(
-                            ErrorKind::InvalidEndOfLine(self.line_number),
+                            ErrorKind::InvalidControlCharacter(self.line_number),
                             None,
                         ));
                     }
-                },
-                '_' => {
-                    // not a no op, read the next char to ensure it is a digit
-                    match sequence.next() {
-                        Some((_, char)) => {
-                            match char {
-                                char if char.is_ascii_digit() == true => {
-                                    return Ok(());
-                                }
-                                _ => {
-                                    // Invalid
-                                    return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
-                                }
-                            }
-                        },
-                        None => {
-                            // Value ended with a _ which is invalid
-                            return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
-                        }
+                    _ => {
+                        // No Op - part of the string
                     }
-                }
-                _ => {
-                    // Error - invalid character in integer
+                },
+                None => {
+                    // File ended before the string was closed
                     return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
                 }
-            },
-            None => {
-                // File ended while reading an integer, this is valid the end of the file denotes the end of the float
-                self.set_value_end(data_to_parse.len());
-                self.state = ParserState::FinishedFloat(true, false);
-                // TODO: This could lead to problems like reading the next character of the sequence no longer returns a null
-                // return Ok(self.build_integer_pair(data_to_parse)?);
-                return Ok(());
             }
         }
     }
 
-    fn process_read_string_state<'a>(
+    /// A value is a date or time if it starts with the year of a date or the hour of a time, ie 1979- or 07:
+    fn is_date_time(value: &str) -> bool {
+        let bytes = value.as_bytes();
+        let is_digits = |digits: usize| bytes.len() > digits && bytes[..digits].iter().all(u8::is_ascii_digit);
+        (is_digits(4) && bytes[4] == b'-') || (is_digits(2) && bytes[2] == b':')
+    }
+
+    fn process_read_date_time_state(
         &mut self,
-        sequence: &mut std::iter::Enumerate<std::str::Chars>,
+        data_to_parse: &str,
+        value_start: usize,
     ) -> Result<(), Error> {
-        match sequence.next() {
-            Some((index, char)) => match char {
-                '"' => {
-                    // End of the string
-                    // We are now scanning for the end of line
-                    println!("Found end of string at {}", index);
-                    // self.state = ParserState::AfterValue;
-                    self.set_value_end(index + self.position);
-                    // *value_ends = index + self.position;
-                    self.position += index + 1;
-                    self.state = ParserState::FinishedString;
-                    // return Ok(self.build_string_pair(data_to_parse)?);
+        loop {
+            match self.peek_char(data_to_parse) {
+                Some((index, char)) => match char {
+                    '0'..='9' | '-' | ':' | '.' | '+' | 'T' | 't' | 'Z' | 'z' => {
+                        self.next_char(data_to_parse);
+                    }
+                    ' ' if index - value_start == 10
+                        && data_to_parse[index + 1..].starts_with(|char: char| char.is_ascii_digit()) =>
+                    {
+                        // A space can seperate the date and the time, ie 1979-05-27 07:32:00
+                        self.next_char(data_to_parse);
+                    }
+                    char if Parser::is_value_terminator(char) => {
+                        self.set_value_end(index);
+                        self.state = ParserState::FinishedDateTime;
+                        return Ok(());
+                    }
+                    _ => {
+                        return Err(Error::new(ErrorKind::InvalidDateTime(self.line_number), None));
+                    }
+                },
+                None => {
+                    // File ended while reading the date, this is valid the end of the file denotes the end of the value
+                    self.set_value_end(data_to_parse.len());
+                    self.state = ParserState::FinishedDateTime;
                     return Ok(());
                 }
-                '\n' | '\r' => {
-                    // End of line without ending the string - this is invalid to read but not to produce
-                    // TODO: This is supported with literal strings
-                    return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
+            }
+        }
+    }
+
+    /// Builds the dotted name that has been read, ie a.b.c
+    fn build_name<'a>(&mut self, data_to_parse: &'a str) -> Result<Vec<Cow<'a, str>>, Error> {
+        let line_number = self.name_line;
+        if let (Some(first), Some(last)) = (self.name_parts.first(), self.name_parts.last()) {
+            self.name_span = first.0..last.1;
+        }
+        let name = self
+            .name_parts
+            .iter()
+            .map(|&(name_start, name_end, is_basic_string)| {
+                let name = &data_to_parse[name_start..name_end];
+                if is_basic_string {
+                    return Parser::unescape_basic_string(name, name_start, line_number);
                 }
-                // TODO: Escaped characters are supported?
-                // An escaped character necessitates copying the string to a new value since we need to convert the escapes into actual characters
-                // TODO: Invalid characters inside a string are there any?? - any unsupported escaped characters
-                _ => {
-                    // A character - there are some characters that will be illegal
-                    println!("Got {} as part of a string", char);
-                    return Ok(());
+                Ok(Cow::Borrowed(name))
+            })
+            .collect();
+        self.built_name_parts = std::mem::take(&mut self.name_parts);
+        name
+    }
+
+    /// Replaces the escapes in a basic string with the characters they represent, the string is only copied if it contains an escape
+    /// The index and line that the string starts at in the document are used to report an invalid escape
+    fn unescape_basic_string(
+        value: &str,
+        value_start: usize,
+        line_number: usize,
+    ) -> Result<Cow<'_, str>, Error> {
+        if !value.contains('\\') {
+            return Ok(Cow::Borrowed(value));
+        }
+        let mut unescaped = String::with_capacity(value.len());
+        let mut chars = value.chars();
+        while let Some(char) = chars.next() {
+            if char != '\\' {
+                unescaped.push(char);
+                continue;
+            }
+            let escape_start = value.len() - chars.as_str().len() - 1;
+            // A \ at the end of a line in a multi-line string removes the newline and any whitespace that follows it
+            let rest = chars.as_str();
+            let after_whitespace = rest.trim_start_matches([' ', '\t']);
+            if after_whitespace.starts_with('\n') || after_whitespace.starts_with("\r\n") {
+                chars = after_whitespace
+                    .trim_start_matches([' ', '\t', '\n', '\r'])
+                    .chars();
+                continue;
+            }
+            let escaped_char = match chars.next() {
+                Some('b') => Some('\u{8}'),
+                Some('t') => Some('\t'),
+                Some('n') => Some('\n'),
+                Some('f') => Some('\u{C}'),
+                Some('r') => Some('\r'),
+                Some('"') => Some('"'),
+                Some('\\') => Some('\\'),
+                Some('u') => Parser::read_unicode_escape(&mut chars, 4),
+                Some('U') => Parser::read_unicode_escape(&mut chars, 8),
+                _ => None,
+            };
+            match escaped_char {
+                Some(escaped_char) => unescaped.push(escaped_char),
+                None => {
+                    // The error covers the escape, which may be on a later line of a multi-line string
+                    let escape_end = value.len() - chars.as_str().len();
+                    let line_number = line_number + value[..escape_start].matches('\n').count();
+                    return Err(Error::new(ErrorKind::InvalidEscape(line_number), None)
+                        .with_span(value_start + escape_start..value_start + escape_end));
                 }
-            },
-            None => {
-                // We have reached the end of the file
-                // This is invalid a string is only valid if it is ended with a "
-                return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
             }
         }
+        Ok(Cow::Owned(unescaped))
+    }
+
+    /// Reads the hex digits of a \u or \U escape, None is returned if the escape is invalid
+    fn read_unicode_escape(chars: &mut std::str::Chars, digits: usize) -> Option<char> {
+        let mut code_point = 0;
+        for _ in 0..digits {
+            code_point = code_point * 16 + chars.next()?.to_digit(16)?;
+        }
+        // Surrogates and values above 10FFFF are not unicode scalar values
+        char::from_u32(code_point)
     }
 
-    fn build_bool_pair<'a>(
+    fn build_table_statement<'a>(
         &mut self,
-        bool_value: bool,
         data_to_parse: &'a str,
-    ) -> Result<TomlPair<'a>, Error> {
-        let name = &data_to_parse[self.name_start()..self.name_end()];
+        is_array: bool,
+    ) -> Result<TomlStatement<'a>, Error> {
+        let table_name = self.build_name(data_to_parse)?;
+        // Text after a table header is inside the table that was opened, not the name of a pair
+        self.built_name_parts.clear();
+        if is_array {
+            return Ok(TomlStatement::ArrayOfTables(table_name));
+        }
+        Ok(TomlStatement::Table(table_name))
+    }
+
+    fn build_bool_value<'a>(&mut self, bool_value: bool) -> TomlValue<'a> {
         self.value_start();
         self.value_end();
-        let toml_pair = TomlPair::new(name, TomlValue::Bool(bool_value));
-        Ok(toml_pair)
+        TomlValue::Bool(bool_value)
     }
 
-    fn build_integer_pair<'a>(&mut self, data_to_parse: &'a str) -> Result<TomlPair<'a>, Error> {
-        let value = &data_to_parse[self.value_start()..self.value_end()];
-        let name = &data_to_parse[self.name_start()..self.name_end()];
-        // Positive case only
-        let mut integer = 0;
-        for char in value.chars() {
-            // Here we are converting the sring to a base 10 u64
-
-            let value = match char {
-            
-                '0'..='9' => char as i64 - '0' as i64,
-                // An underscore is a no op
-                '_' => continue,
-                _ => unreachable!("Invalid character was found while building an integer pair, all validation should have been done while parsing the integer"),
-            
-            };
-            integer *= 10;
-            integer += value;
-        }
-        return Ok(TomlPair::new(name, TomlValue::Integer(integer)));
+    /// Appends a digit to an integer, the digit is subtracted when building a negative integer so that i64::MIN can be read
+    fn push_digit(&self, integer: i64, base: i64, digit: i64, is_negative: bool) -> Result<i64, Error> {
+        let shifted = integer.checked_mul(base);
+        let integer = match is_negative {
+            true => shifted.and_then(|integer| integer.checked_sub(digit)),
+            false => shifted.and_then(|integer| integer.checked_add(digit)),
+        };
+        integer.ok_or_else(|| Error::new(ErrorKind::IntegerOverflow(self.line_number), None))
     }
 
-    fn build_negative_integer_pair<'a>(&mut self, data_to_parse: &'a str) -> Result<TomlPair<'a>, Error> {
+    fn build_integer_value<'a>(&mut self, data_to_parse: &'a str) -> Result<TomlValue<'a>, Error> {
         let value = &data_to_parse[self.value_start()..self.value_end()];
-        let name = &data_to_parse[self.name_start()..self.name_end()];
-        // Negative case only
+        let (is_negative, value) = match value.as_bytes().first() {
+            Some(b'-') => (true, &value[1..]),
+            Some(b'+') => (false, &value[1..]),
+            _ => (false, value),
+        };
         let mut integer = 0;
         for char in value.chars() {
             // Here we are converting the sring to a base 10 u64
 
             let value = match char {
-            
+
                 '0'..='9' => char as i64 - '0' as i64,
                 // An underscore is a no op
                 '_' => continue,
-                _ => unreachable!("Invalid character was found while building an integer pair, all validation should have been done while parsing the integer, character was {}", char),
-            
+                _ => return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None)),
+
             };
-            // TODO: Check for integer overflow
-            integer *= 10;
-            integer -= value;
+            integer = self.push_digit(integer, 10, value, is_negative)?;
         }
-        return Ok(TomlPair::new(name, TomlValue::Integer(integer)));
+        Ok(TomlValue::Integer(integer))
     }
 
-    fn build_binary_integer_pair<'a>(&mut self, data_to_parse: &'a str) -> Result<TomlPair<'a>, Error> {
+    fn build_binary_integer_value<'a>(&mut self, data_to_parse: &'a str) -> Result<TomlValue<'a>, Error> {
         let value = &data_to_parse[self.value_start()..self.value_end()];
-        let name = &data_to_parse[self.name_start()..self.name_end()];
         // Positive case only
         let mut integer = 0;
         for char in value.chars() {
             // Here we are converting the sring to a base 10 u64
 
-            let bin_value = match char {            
+            let bin_value = match char {
                 '0'..='1' => char as i64 - '0' as i64,
                 // An underscore is a no op
                 '_' => continue,
-                _ => unreachable!("Invalid character was found while building a binary integer pair, all validation should have been done while parsing the integer"),            
+                _ => return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None)),
             };
-            
-            integer <<= 1;
-            integer += bin_value;
+
+            integer = self.push_digit(integer, 2, bin_value, false)?;
         }
-        return Ok(TomlPair::new(name, TomlValue::Integer(integer)));
+        Ok(TomlValue::Integer(integer))
     }
 
-    fn build_hex_integer_pair<'a>(&mut self, data_to_parse: &'a str) -> Result<TomlPair<'a>, Error> {
+    fn build_hex_integer_value<'a>(&mut self, data_to_parse: &'a str) -> Result<TomlValue<'a>, Error> {
         let value = &data_to_parse[self.value_start()..self.value_end()];
-        let name = &data_to_parse[self.name_start()..self.name_end()];
         // Positive case only
         let mut integer = 0;
         for char in value.chars() {
             // Here we are converting the sring to a base 10 u64
 
-            let hex_value = match char {            
+            let hex_value = match char {
                 '0'..='9' => char as i64 - '0' as i64,
                 'a'..='f' => char as i64 - 'a' as i64 + 10,
                 'A'..='F' => char as i64 - 'A' as i64 + 10,
                 // An underscore is a no op
                 '_' => continue,
-                _ => unreachable!("Invalid character was found while building a binary integer pair, all validation should have been done while parsing the integer"),            
+                _ => return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None)),
             };
-            
-            integer *= 16;
-            integer += hex_value;
+
+            integer = self.push_digit(integer, 16, hex_value, false)?;
         }
-        return Ok(TomlPair::new(name, TomlValue::Integer(integer)));
+        Ok(TomlValue::Integer(integer))
     }
 
-    fn build_octal_integer_pair<'a>(&mut self, data_to_parse: &'a str) -> Result<TomlPair<'a>, Error> {
+    fn build_octal_integer_value<'a>(&mut self, data_to_parse: &'a str) -> Result<TomlValue<'a>, Error> {
         let value = &data_to_parse[self.value_start()..self.value_end()];
-        let name = &data_to_parse[self.name_start()..self.name_end()];
         // Positive case only
         let mut integer = 0;
         for char in value.chars() {
             // Here we are converting the sring to a base 10 u64
 
-            let octal_value = match char {            
+            let octal_value = match char {
                 '0'..='7' => char as i64 - '0' as i64,
                 // An underscore is a no op
                 '_' => continue,
-                _ => unreachable!("Invalid character was found while building a binary integer pair, all validation should have been done while parsing the integer"),            
+                _ => return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None)),
             };
-            
-            integer *= 8;
-            integer += octal_value;
+
+            integer = self.push_digit(integer, 8, octal_value, false)?;
         }
-        return Ok(TomlPair::new(name, TomlValue::Integer(integer)));
+        Ok(TomlValue::Integer(integer))
     }
 
-    fn build_float_pair<'a>(&mut self, data_to_parse: &'a str) -> Result<TomlPair<'a>, Error> {
+    fn build_float_value<'a>(&mut self, data_to_parse: &'a str) -> Result<TomlValue<'a>, Error> {
         let value = &data_to_parse[self.value_start()..self.value_end()];
-        let name = &data_to_parse[self.name_start()..self.name_end()];
         // Parsing a string to float is error prone and complex
         // TODO: Custom parsing allows us to avoid the string copy
         let mut copied_string = value.to_string();
         copied_string.retain(|c| c != '_');
-        let float = match copied_string.parse::<f64>() {
-            Ok(integer) => integer,
-            Err(error) => {
-                // This is somewhat unreachable since we will see the error before this point when reading the toml file
-                return Err(Error::new(
-                    ErrorKind::InvalidValue(self.line_number),
-                    Some(Box::new(error)),
-                ));
-            }
+        // The special values are matched here so that the sign of nan is kept
+        let float = match copied_string.as_str() {
+            "inf" | "+inf" => f64::INFINITY,
+            "-inf" => f64::NEG_INFINITY,
+            "nan" | "+nan" => f64::NAN,
+            "-nan" => -f64::NAN,
+            _ => match copied_string.parse::<f64>() {
+                Ok(integer) => integer,
+                Err(error) => {
+                    // This is somewhat unreachable since we will see the error before this point when reading the toml file
+                    return Err(Error::new(
+                        ErrorKind::InvalidValue(self.line_number),
+                        Some(Box::new(error)),
+                    ));
+                }
+            },
         };
-        return Ok(TomlPair::new(name, TomlValue::Float(float)));
+        Ok(TomlValue::Float(float))
+    }
+
+    fn build_date_time_value<'a>(&mut self, data_to_parse: &'a str) -> Result<TomlValue<'a>, Error> {
+        let value = &data_to_parse[self.value_start()..self.value_end()];
+        let line_number = self.line_number;
+        let invalid_date_time = || Error::new(ErrorKind::InvalidDateTime(line_number), None);
+        // A time on its own, ie 07:32:00
+        if value.as_bytes()[2] == b':' {
+            let time = LocalTime::parse(value).ok_or_else(invalid_date_time)?;
+            return Ok(TomlValue::LocalTime(time));
+        }
+        if value.len() <= 10 {
+            let date = LocalDate::parse(value).ok_or_else(invalid_date_time)?;
+            return Ok(TomlValue::LocalDate(date));
+        }
+        // The date and time can be seperated by a T or a space
+        let (date, time) = value.split_at(10);
+        if !time.starts_with(['T', 't', ' ']) {
+            return Err(invalid_date_time());
+        }
+        let date = LocalDate::parse(date).ok_or_else(invalid_date_time)?;
+        let time = &time[1..];
+        match time.find(['Z', 'z', '+', '-']) {
+            Some(offset_start) => {
+                let offset = OffsetDateTime::parse_offset(&time[offset_start..])
+                    .ok_or_else(invalid_date_time)?;
+                let time = LocalTime::parse(&time[..offset_start]).ok_or_else(invalid_date_time)?;
+                Ok(TomlValue::OffsetDateTime(OffsetDateTime { date, time, offset }))
+            }
+            None => {
+                let time = LocalTime::parse(time).ok_or_else(invalid_date_time)?;
+                Ok(TomlValue::LocalDateTime(LocalDateTime { date, time }))
+            }
+        }
     }
 
-    fn build_string_pair<'a>(&mut self, data_to_parse: &'a str) -> Result<TomlPair<'a>, Error> {
+    fn build_literal_string_value<'a>(&mut self, data_to_parse: &'a str) -> TomlValue<'a> {
         let value = &data_to_parse[self.value_start()..self.value_end()];
-        let name = &data_to_parse[self.name_start()..self.name_end()];
-        return Ok(TomlPair::new(name, TomlValue::String(value)));
+        TomlValue::String(Cow::Borrowed(value))
     }
 
-    fn process_reading_octal<'a>(
-        &mut self,
-        sequence: &mut std::iter::Enumerate<std::str::Chars>,
-        data_to_parse: &'a str,
-    ) -> Result<(), Error> {
-        match sequence.next() {
+    fn build_string_value<'a>(&mut self, data_to_parse: &'a str) -> Result<TomlValue<'a>, Error> {
+        let value_start = self.value_start();
+        let value = &data_to_parse[value_start..self.value_end()];
+        // The string has been read so the parser is on the line the string ends on
+        let line_number = self.line_number - value.matches('\n').count();
+        let value = Parser::unescape_basic_string(value, value_start, line_number)?;
+        Ok(TomlValue::String(value))
+    }
+
+    fn process_reading_octal(&mut self, data_to_parse: &str) -> Result<(), Error> {
+        match self.peek_char(data_to_parse) {
             Some((index, char)) => match char {
-                char if char.is_ascii_digit() == true => match char {
-                    '8' | '9' => {
-                        return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
-                    }
-                    _ => return Ok(()),
-                },
-                ' ' | '\t' => {
-                    // Whitespace means the integer ended.
-                    // This means we are still looking for a new line but the integer is done
-                    self.state = ParserState::FinishedOctalInteger(false, false);
-                    self.set_value_end(index + self.position);
-                    self.position += index;
-                    // Moving to AfterValue state
-                    // self.state = ParserState::AfterValue;
-                    // return Ok(self.build_integer_pair(data_to_parse)?);
-                    return Ok(());
-                }
-                '.' => {
-                    // TODO: Floats are invalid here
-                    unimplemented!("No support for floats");
+                '8' | '9' => {
+                    Err(Error::new(ErrorKind::InvalidValue(self.line_number), None))
                 }
-                '_' => {
-                    // not a no op, read the next char to ensure it is a digit
-                    return Ok(());
-                    // TODO: Each underscore must be surrounded by at least one digit on each side
-                    // TODO: This requirement seems flawed, it appears to be a way to help parsers but really just makes parsing more complex
-                    // This is a no op, the string to integer functions are _ aware
+                char if char.is_digit(8) => {
+                    self.next_char(data_to_parse);
+                    Ok(())
                 }
-                '\n' => {
-                    // End of integer
-                    self.state = ParserState::FinishedOctalInteger(false, true);
-                    // Our state transition when finishing a value is based on the character that was read during the transition to finish
-                    // (state, previous_character)
-                    self.line_number += 1;
-                    self.set_value_end(index + self.position);
-                    self.position += index;
-                    // return Ok(self.build_integer_pair(data_to_parse)?);
-                    return Ok(());
+                char if Parser::is_value_terminator(char) => {
+                    // Whitespace, a comment or a new line means the integer ended.
+                    // The character ending the integer is processed by the AfterValue state
+                    self.finish_prefixed_integer(
+                        data_to_parse,
+                        index,
+                        8,
+                        ParserState::FinishedOctalInteger,
+                    )
                 }
-                '\r' => {
-                    // Next character must be \n and then return pair
-                    if let Some((index, '\n')) = sequence.next() {
-                        self.state = ParserState::FinishedOctalInteger(false, true);
-                        self.line_number += 1;
-                        // We reduce the length by one to account for the two byte eol sequence
-                        self.set_value_end(index + self.position - 1);
-                        self.position += index;
-                        // We set the previous token so that when we move from finished state we move to normal rather than after value
-                        // return Ok(self.build_integer_pair(data_to_parse)?);
-                        return Ok(());
-                        // Integer is done and we are moving to NewLine state
-                    } else {
-                        // Error - invalid integer because of invalid end of line
-                        return Err(Error::new(
-                            ErrorKind::InvalidEndOfLine(self.line_number),
-                            None,
-                        ));
-                    }
+                '_' => {
+                    self.process_number_underscore(data_to_parse, index, 8)
                 }
                 _ => {
-                    // Error - invalid character in integer
-                    return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
+                    // Error - invalid character in integer, including a . since only decimal numbers can be floats
+                    Err(Error::new(ErrorKind::InvalidValue(self.line_number), None))
                 }
             },
             None => {
                 // File ended while reading an integer, this is valid the end of the file denotes the end of the integer
-                self.set_value_end(data_to_parse.len());
-                self.state = ParserState::FinishedOctalInteger(true, false);
-                // TODO: This could lead to problems like reading the next character of the sequence no longer returns a null
-                // return Ok(self.build_integer_pair(data_to_parse)?);
-                return Ok(());
+                self.finish_prefixed_integer(
+                    data_to_parse,
+                    data_to_parse.len(),
+                    8,
+                    ParserState::FinishedOctalInteger,
+                )
             }
         }
     }
 
-    fn process_reading_binary<'a>(
-        &mut self,
-        sequence: &mut std::iter::Enumerate<std::str::Chars>,
-        data_to_parse: &'a str,
-    ) -> Result<(), Error> {
-        match sequence.next() {
+    fn process_reading_binary(&mut self, data_to_parse: &str) -> Result<(), Error> {
+        match self.peek_char(data_to_parse) {
             Some((index, char)) => match char {
-                char if char.is_ascii_digit() == true => match char {
-                    '0' | '1' => {
-                        return Ok(());
-                    }
-                    _ => return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None)),
-                },
-                ' ' | '\t' => {
-                    // Whitespace means the integer ended.
-                    // This means we are still looking for a new line but the integer is done
-                    self.state = ParserState::FinishedBinaryInteger(false, false);
-                    self.set_value_end(index + self.position);
-                    self.position += index;
-                    // Moving to AfterValue state
-                    // self.state = ParserState::AfterValue;
-                    // return Ok(self.build_integer_pair(data_to_parse)?);
-                    return Ok(());
+                char if char.is_digit(2) => {
+                    self.next_char(data_to_parse);
+                    Ok(())
                 }
-                '.' => {
-                    unimplemented!("No support for floats");
-                }
-                '_' => {
-                    // not a no op, read the next char to ensure it is a digit
-                    return Ok(());
-                    // TODO: Each underscore must be surrounded by at least one digit on each side
-                    // TODO: This requirement seems flawed, it appears to be a way to help parsers but really just makes parsing more complex
-                    // This is a no op, the string to integer functions are _ aware
+                char if char.is_ascii_digit() => {
+                    Err(Error::new(ErrorKind::InvalidValue(self.line_number), None))
                 }
-                '\n' => {
-                    // End of integer
-                    self.state = ParserState::FinishedBinaryInteger(false, true);
-                    // Our state transition when finishing a value is based on the character that was read during the transition to finish
-                    // (state, previous_character)
-                    self.line_number += 1;
-                    self.set_value_end(index + self.position);
-                    self.position += index;
-                    // return Ok(self.build_integer_pair(data_to_parse)?);
-                    return Ok(());
+                char if Parser::is_value_terminator(char) => {
+                    // Whitespace, a comment or a new line means the integer ended.
+                    // The character ending the integer is processed by the AfterValue state
+                    self.finish_prefixed_integer(
+                        data_to_parse,
+                        index,
+                        2,
+                        ParserState::FinishedBinaryInteger,
+                    )
                 }
-                '\r' => {
-                    // Next character must be \n and then return pair
-                    if let Some((index, '\n')) = sequence.next() {
-                        self.state = ParserState::FinishedBinaryInteger(false, true);
-                        self.line_number += 1;
-                        // We reduce the length by one to account for the two byte eol sequence
-                        self.set_value_end(index + self.position - 1);
-                        self.position += index;
-                        // We set the previous token so that when we move from finished state we move to normal rather than after value
-                        // return Ok(self.build_integer_pair(data_to_parse)?);
-                        return Ok(());
-                        // Integer is done and we are moving to NewLine state
-                    } else {
-                        // Error - invalid integer because of invalid end of line
-                        return Err(Error::new(
-                            ErrorKind::InvalidEndOfLine(self.line_number),
-                            None,
-                        ));
-                    }
+                '_' => {
+                    self.process_number_underscore(data_to_parse, index, 2)
                 }
                 _ => {
-                    // Error - invalid character in integer
-                    return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
+                    // Error - invalid character in integer, including a . since only decimal numbers can be floats
+                    Err(Error::new(ErrorKind::InvalidValue(self.line_number), None))
                 }
             },
             None => {
                 // File ended while reading an integer, this is valid the end of the file denotes the end of the integer
-                self.set_value_end(data_to_parse.len());
-                self.state = ParserState::FinishedBinaryInteger(true, false);
-                // TODO: This could lead to problems like reading the next character of the sequence no longer returns a null
-                // return Ok(self.build_integer_pair(data_to_parse)?);
-                return Ok(());
+                self.finish_prefixed_integer(
+                    data_to_parse,
+                    data_to_parse.len(),
+                    2,
+                    ParserState::FinishedBinaryInteger,
+                )
             }
         }
     }
 
-    fn process_reading_hex<'a>(
-        &mut self,
-        sequence: &mut std::iter::Enumerate<std::str::Chars>,
-        data_to_parse: &'a str,
-    ) -> Result<(), Error> {
-        match sequence.next() {
+    fn process_reading_hex(&mut self, data_to_parse: &str) -> Result<(), Error> {
+        match self.peek_char(data_to_parse) {
             Some((index, char)) => match char {
-                char if char.is_ascii_digit() == true => return Ok(()),
-                'a' | 'b' | 'c' | 'd' | 'e' | 'f' => return Ok(()),
-                'A' | 'B' | 'C' | 'D' | 'E' | 'F' => return Ok(()),
-                ' ' | '\t' => {
-                    // Whitespace means the integer ended.
-                    // This means we are still looking for a new line but the integer is done
-                    self.state = ParserState::FinishedHexInteger(false, false);
-                    self.set_value_end(index + self.position);
-                    self.position += index;
-                    return Ok(());
-                },
-                '.' => {
-                    unimplemented!("No support for floats");
-                },
+                char if char.is_ascii_hexdigit() => {
+                    self.next_char(data_to_parse);
+                    Ok(())
+                }
+                char if Parser::is_value_terminator(char) => {
+                    // Whitespace, a comment or a new line means the integer ended.
+                    // The character ending the integer is processed by the AfterValue state
+                    self.finish_prefixed_integer(
+                        data_to_parse,
+                        index,
+                        16,
+                        ParserState::FinishedHexInteger,
+                    )
+                }
                 '_' => {
-                    // not a no op, read the next char to ensure it is a digit
-                    return Ok(());
-                    // TODO: Each underscore must be surrounded by at least one digit on each side
-                    // TODO: This requirement seems flawed, it appears to be a way to help parsers but really just makes parsing more complex
-                    // This is a no op, the string to integer functions are _ aware
-                },
-                '\n' => {
-                    // End of integer
-                    self.state = ParserState::FinishedHexInteger(false, true);
-                    // Our state transition when finishing a value is based on the character that was read during the transition to finish
-                    // (state, previous_character)
-                    self.line_number += 1;
-                    self.set_value_end(index + self.position);
-                    self.position += index;
-                    // return Ok(self.build_integer_pair(data_to_parse)?);
-                    return Ok(());
-                },
-                '\r' => {
-                    // Next character must be \n and then return pair
-                    if let Some((index, '\n')) = sequence.next() {
-                        self.state = ParserState::FinishedHexInteger(false, true);
-                        self.line_number += 1;
-                        // We reduce the length by one to account for the two byte eol sequence
-                        self.set_value_end(index + self.position - 1);
-                        self.position += index;
-                        // We set the previous token so that when we move from finished state we move to normal rather than after value
-                        // return Ok(self.build_integer_pair(data_to_parse)?);
-                        return Ok(());
-                        // Integer is done and we are moving to NewLine state
-                    } else {
-                        // Error - invalid integer because of invalid end of line
-                        return Err(Error::new(
-                            ErrorKind::InvalidEndOfLine(self.line_number),
-                            None,
-                        ));
-                    }
-                },
+                    self.process_number_underscore(data_to_parse, index, 16)
+                }
                 _ => {
-                    // Error - invalid character in integer
-                    return Err(Error::new(ErrorKind::InvalidValue(self.line_number), None));
+                    // Error - invalid character in integer, including a . since only decimal numbers can be floats
+                    Err(Error::new(ErrorKind::InvalidValue(self.line_number), None))
                 }
             },
             None => {
                 // File ended while reading an integer, this is valid the end of the file denotes the end of the integer
-                self.set_value_end(data_to_parse.len());
-                self.state = ParserState::FinishedHexInteger(true, false);
-                return Ok(());
+                self.finish_prefixed_integer(
+                    data_to_parse,
+                    data_to_parse.len(),
+                    16,
+                    ParserState::FinishedHexInteger,
+                )
             }
         }
     }
@@ -0,0 +1,46 @@
+use std::borrow::Cow;
+
+use super::{TomlTable, TomlValue};
+
+impl<'a> TomlValue<'a> {
+    /// Copies any names and strings that are borrowed from the document so that the value no longer depends on it
+    pub fn into_owned(self) -> TomlValue<'static> {
+        match self {
+            TomlValue::String(string) => TomlValue::String(Cow::Owned(string.into_owned())),
+            TomlValue::Integer(integer) => TomlValue::Integer(integer),
+            TomlValue::Float(float) => TomlValue::Float(float),
+            TomlValue::Bool(bool_value) => TomlValue::Bool(bool_value),
+            TomlValue::OffsetDateTime(date_time) => TomlValue::OffsetDateTime(date_time),
+            TomlValue::LocalDateTime(date_time) => TomlValue::LocalDateTime(date_time),
+            TomlValue::LocalDate(date) => TomlValue::LocalDate(date),
+            TomlValue::LocalTime(time) => TomlValue::LocalTime(time),
+            TomlValue::Array(values) => {
+                TomlValue::Array(values.into_iter().map(TomlValue::into_owned).collect())
+            }
+            TomlValue::Table(table) => TomlValue::Table(into_owned_table(table)),
+        }
+    }
+
+    /// Sorts the names of the table and of every table inside of it, arrays keep their order
+    pub fn sort_keys(&mut self) {
+        match self {
+            TomlValue::Table(table) => sort_table(table),
+            TomlValue::Array(values) => values.iter_mut().for_each(TomlValue::sort_keys),
+            _ => {}
+        }
+    }
+}
+
+/// Copies the names and values of a table so that it no longer depends on the document
+pub(crate) fn into_owned_table(table: TomlTable<'_>) -> TomlTable<'static> {
+    table
+        .into_iter()
+        .map(|(name, value)| (Cow::Owned(name.into_owned()), value.into_owned()))
+        .collect()
+}
+
+/// Sorts the names of the table and of every table inside of it
+pub(crate) fn sort_table(table: &mut TomlTable<'_>) {
+    table.sort_keys();
+    table.values_mut().for_each(TomlValue::sort_keys);
+}